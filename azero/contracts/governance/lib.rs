@@ -1,7 +1,9 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
+pub use self::governance::{GovernanceError, Proposal};
+
 #[ink::contract]
-mod governance {
+pub mod governance {
     use ink::{
         env::{
             call::{build_call, ExecutionInput},
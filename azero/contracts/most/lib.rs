@@ -13,15 +13,13 @@ pub mod most {
             call::{build_call, ExecutionInput},
             set_code_hash, DefaultEnvironment, Error as InkEnvError,
         },
-        prelude::{format, string::String, vec, vec::Vec},
+        prelude::{format, string::String, vec::Vec},
         storage::{traits::ManualKey, Lazy, Mapping},
     };
     use psp22::PSP22Error;
     use psp22_traits::{Burnable, Mintable};
     use scale::{Decode, Encode};
-    use shared::{concat_u8_arrays, keccak256, Keccak256HashOutput as HashedRequest, Selector};
-
-    type CommitteeId = u128;
+    use shared::{CommitteeId, CrosschainRequest, Keccak256HashOutput as HashedRequest, Selector};
 
     const GAS_ORACLE_MAX_AGE: u64 = 24 * 60 * 60 * 1000; // 1 day
     const ORACLE_CALL_GAS_LIMIT: u64 = 2_000_000_000;
@@ -295,80 +293,33 @@ pub mod most {
             request_nonce: u128,
         ) -> Result<(), MostError> {
             let caller = self.env().caller();
-            self.only_committee_member(committee_id, caller)?;
-
-            let data = self.data()?;
-
-            // Don't revert if the request has already been processed as
-            // such a call can be made during regular guardian operation.
-            if self.processed_requests.contains(request_hash) {
-                self.env().emit_event(SignedProcessedRequest {
-                    request_hash,
-                    signer: caller,
-                });
-                return Ok(());
-            }
-
-            let bytes = concat_u8_arrays(vec![
-                &committee_id.to_le_bytes(),
-                &dest_token_address,
-                &amount.to_le_bytes(),
-                &dest_receiver_address,
-                &request_nonce.to_le_bytes(),
-            ]);
-
-            let hash = keccak256(&bytes);
-
-            if !request_hash.eq(&hash) {
-                return Err(MostError::HashDoesNotMatchData);
-            }
-
-            if self.signatures.contains((request_hash, caller)) {
-                return Err(MostError::RequestAlreadySigned);
-            }
-
-            let mut request = self.pending_requests.get(request_hash).unwrap_or_default(); //  {
-
-            // record vote
-            request.signature_count += 1;
-            self.signatures.insert((request_hash, caller), &());
-
-            self.env().emit_event(RequestSigned {
-                signer: caller,
+            self.record_vote(
+                caller,
                 request_hash,
-            });
-
-            let signature_threshold = self
-                .signature_thresholds
-                .get(data.committee_id)
-                .ok_or(MostError::InvalidThreshold)?;
-
-            if request.signature_count >= signature_threshold {
-                self.mint_to(
-                    dest_token_address.into(),
-                    dest_receiver_address.into(),
-                    amount,
-                )?;
-
-                // bootstrap account with pocket money
-                // NOTE: we don't revert on a failure!
-                _ = self
-                    .env()
-                    .transfer(dest_receiver_address.into(), data.pocket_money);
-
-                // mark it as processed
-                self.processed_requests.insert(request_hash, &());
-                self.pending_requests.remove(request_hash);
-
-                self.env().emit_event(RequestProcessed {
-                    request_hash,
+                CrosschainRequest {
+                    committee_id,
                     dest_token_address,
-                });
-            } else {
-                self.pending_requests.insert(request_hash, &request);
-            }
+                    amount,
+                    dest_receiver_address,
+                    request_nonce,
+                },
+            )
+        }
 
-            Ok(())
+        /// Casts the caller's votes on many requests at once
+        ///
+        /// Every vote is handled as in `receive_request` and returns its own result,
+        /// a failed vote does not affect the others.
+        #[ink(message)]
+        pub fn receive_requests(
+            &mut self,
+            requests: Vec<(HashedRequest, CrosschainRequest)>,
+        ) -> Vec<Result<(), MostError>> {
+            let caller = self.env().caller();
+            requests
+                .into_iter()
+                .map(|(request_hash, request)| self.record_vote(caller, request_hash, request))
+                .collect()
         }
 
         /// Request payout of rewards for signing & relaying cross-chain transfers.
@@ -540,6 +491,18 @@ pub mod most {
             }
         }
 
+        /// Query whether the request has collected enough signatures and has been executed
+        #[ink(message)]
+        pub fn is_request_processed(&self, request_hash: HashedRequest) -> bool {
+            self.processed_requests.contains(request_hash)
+        }
+
+        /// Query whether the account has already signed the request
+        #[ink(message)]
+        pub fn has_signed_request(&self, request_hash: HashedRequest, account: AccountId) -> bool {
+            self.signatures.contains((request_hash, account))
+        }
+
         /// Returns an error (reverts) if account is not in the committee with `committee_id`
         #[ink(message)]
         pub fn is_in_committee(&self, committee_id: CommitteeId, account: AccountId) -> bool {
@@ -674,6 +637,84 @@ pub mod most {
             psp22.burn_from(from, amount)
         }
 
+        /// Records the vote and executes the request once it has enough signatures
+        ///
+        /// Nothing is written before all checks pass and the tokens are minted,
+        /// so that a failed vote leaves no trace when it is a part of a batch.
+        fn record_vote(
+            &mut self,
+            caller: AccountId,
+            request_hash: HashedRequest,
+            request: CrosschainRequest,
+        ) -> Result<(), MostError> {
+            self.only_committee_member(request.committee_id, caller)?;
+
+            let data = self.data()?;
+
+            // Don't revert if the request has already been processed as
+            // such a call can be made during regular guardian operation.
+            if self.processed_requests.contains(request_hash) {
+                self.env().emit_event(SignedProcessedRequest {
+                    request_hash,
+                    signer: caller,
+                });
+                return Ok(());
+            }
+
+            if !request_hash.eq(&request.azero_hash()) {
+                return Err(MostError::HashDoesNotMatchData);
+            }
+
+            if self.signatures.contains((request_hash, caller)) {
+                return Err(MostError::RequestAlreadySigned);
+            }
+
+            let mut pending_request = self.pending_requests.get(request_hash).unwrap_or_default();
+            pending_request.signature_count += 1;
+
+            let signature_threshold = self
+                .signature_thresholds
+                .get(data.committee_id)
+                .ok_or(MostError::InvalidThreshold)?;
+
+            if pending_request.signature_count >= signature_threshold {
+                self.mint_to(
+                    request.dest_token_address.into(),
+                    request.dest_receiver_address.into(),
+                    request.amount,
+                )?;
+
+                // bootstrap account with pocket money
+                // NOTE: we don't revert on a failure!
+                _ = self
+                    .env()
+                    .transfer(request.dest_receiver_address.into(), data.pocket_money);
+            }
+
+            // record vote
+            self.signatures.insert((request_hash, caller), &());
+
+            self.env().emit_event(RequestSigned {
+                signer: caller,
+                request_hash,
+            });
+
+            if pending_request.signature_count >= signature_threshold {
+                // mark it as processed
+                self.processed_requests.insert(request_hash, &());
+                self.pending_requests.remove(request_hash);
+
+                self.env().emit_event(RequestProcessed {
+                    request_hash,
+                    dest_token_address: request.dest_token_address,
+                });
+            } else {
+                self.pending_requests.insert(request_hash, &pending_request);
+            }
+
+            Ok(())
+        }
+
         fn data(&self) -> Result<Data, MostError> {
             self.data.get().ok_or(MostError::CorruptedStorage)
         }
@@ -682,9 +723,10 @@ pub mod most {
     #[cfg(test)]
     mod tests {
         use ink::env::{
-            test::{default_accounts, set_caller},
+            test::{default_accounts, recorded_events, set_caller},
             DefaultEnvironment, Environment,
         };
+        use shared::event_signature_topic;
 
         use super::*;
 
@@ -709,6 +751,31 @@ pub mod most {
             ]
         }
 
+        fn setup_most() -> Most {
+            set_caller::<DefEnv>(default_accounts::<DefEnv>().alice);
+            Most::new(
+                guardian_accounts(),
+                THRESHOLD,
+                POCKET_MONEY,
+                RELAY_GAS_USAGE,
+                MIN_FEE,
+                MAX_FEE,
+                DEFAULT_FEE,
+                None,
+            )
+            .expect("Threshold is valid.")
+        }
+
+        fn sample_request() -> CrosschainRequest {
+            CrosschainRequest {
+                committee_id: 0,
+                dest_token_address: [0x1; 32],
+                amount: 1000,
+                dest_receiver_address: [0x2; 32],
+                request_nonce: 7,
+            }
+        }
+
         #[ink::test]
         fn new_fails_on_zero_threshold() {
             set_caller::<DefEnv>(default_accounts::<DefEnv>().alice);
@@ -857,5 +924,109 @@ pub mod most {
             assert_eq!(most.set_committee(vec![accounts.alice], 1), Ok(()));
             assert!(!most.is_in_committee(most.get_current_committee_id().unwrap(), accounts.bob));
         }
+
+        #[ink::test]
+        fn receive_request_accepts_canonical_hash() {
+            let mut most = setup_most();
+            let request = sample_request();
+
+            set_caller::<DefEnv>(guardian_accounts()[0]);
+            assert_eq!(
+                most.receive_request(
+                    request.eth_hash(),
+                    request.committee_id,
+                    request.dest_token_address,
+                    request.amount,
+                    request.dest_receiver_address,
+                    request.request_nonce,
+                ),
+                Err(MostError::HashDoesNotMatchData)
+            );
+            assert_eq!(
+                most.receive_request(
+                    request.azero_hash(),
+                    request.committee_id,
+                    request.dest_token_address,
+                    request.amount,
+                    request.dest_receiver_address,
+                    request.request_nonce,
+                ),
+                Ok(())
+            );
+        }
+
+        #[ink::test]
+        fn has_signed_request_follows_votes() {
+            let mut most = setup_most();
+            let request = sample_request();
+            let request_hash = request.azero_hash();
+
+            set_caller::<DefEnv>(guardian_accounts()[0]);
+            assert_eq!(
+                most.receive_request(
+                    request_hash,
+                    request.committee_id,
+                    request.dest_token_address,
+                    request.amount,
+                    request.dest_receiver_address,
+                    request.request_nonce,
+                ),
+                Ok(())
+            );
+
+            assert!(most.has_signed_request(request_hash, guardian_accounts()[0]));
+            assert!(!most.has_signed_request(request_hash, guardian_accounts()[1]));
+            assert!(!most.is_request_processed(request_hash));
+        }
+
+        #[ink::test]
+        fn receive_requests_returns_result_per_request() {
+            let mut most = setup_most();
+            let request = sample_request();
+            let other_request = CrosschainRequest {
+                request_nonce: 8,
+                ..request
+            };
+
+            set_caller::<DefEnv>(guardian_accounts()[0]);
+            assert_eq!(
+                most.receive_requests(vec![
+                    (request.azero_hash(), request),
+                    (other_request.eth_hash(), other_request),
+                ]),
+                vec![Ok(()), Err(MostError::HashDoesNotMatchData)]
+            );
+
+            assert!(most.has_signed_request(request.azero_hash(), guardian_accounts()[0]));
+            assert!(!most.has_signed_request(other_request.azero_hash(), guardian_accounts()[0]));
+        }
+
+        #[ink::test]
+        fn events_are_identified_by_signature_topic() {
+            let mut most = setup_most();
+            let request = sample_request();
+
+            set_caller::<DefEnv>(guardian_accounts()[0]);
+            assert_eq!(
+                most.receive_requests(vec![(request.azero_hash(), request)]),
+                vec![Ok(())]
+            );
+
+            // The relayer decodes events by their first topic, skipping the event index.
+            let event = recorded_events()
+                .last()
+                .expect("RequestSigned has been emitted");
+            assert_eq!(
+                event.topics[0],
+                event_signature_topic("Most::RequestSigned").to_vec()
+            );
+            assert_eq!(
+                RequestSigned::decode(&mut &event.data[1..]).expect("Event data is valid"),
+                RequestSigned {
+                    request_hash: request.azero_hash(),
+                    signer: guardian_accounts()[0],
+                }
+            );
+        }
     }
 }
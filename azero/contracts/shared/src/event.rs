@@ -0,0 +1,34 @@
+/// First topic of an event emitted by an ink! 4 contract, e.g. `event_signature_topic("Most::RequestSigned")`.
+///
+/// ink! encodes the `<contract>::<event>` path behind an empty prefix, i.e. a zero byte, and uses it as the topic
+/// directly if it fits into 32 bytes. This holds for all events of our contracts, longer paths would be hashed instead,
+/// so they are rejected at compile time.
+pub const fn event_signature_topic(signature: &str) -> [u8; 32] {
+    let signature = signature.as_bytes();
+    assert!(
+        signature.len() < 32,
+        "Event signatures of 32 bytes or more are hashed"
+    );
+
+    let mut topic = [0u8; 32];
+    let mut i = 0;
+    while i < signature.len() {
+        topic[i + 1] = signature[i];
+        i += 1;
+    }
+    topic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_follows_empty_prefix() {
+        let topic = event_signature_topic("Most::RequestSigned");
+
+        assert_eq!(topic[0], 0);
+        assert_eq!(topic[1..20], *b"Most::RequestSigned");
+        assert_eq!(topic[20..], [0; 12]);
+    }
+}
@@ -1,9 +1,13 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
+mod event;
 mod hash;
 mod helpers;
+mod request;
 mod types;
 
+pub use event::event_signature_topic;
 pub use hash::keccak256;
 pub use helpers::concat_u8_arrays;
+pub use request::{CommitteeId, CrosschainRequest};
 pub use types::{CallInput, Keccak256HashOutput, Selector};
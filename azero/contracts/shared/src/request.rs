@@ -0,0 +1,128 @@
+use ink::prelude::{vec, vec::Vec};
+use scale::{Decode, Encode};
+
+use crate::{concat_u8_arrays, keccak256, Keccak256HashOutput};
+
+pub type CommitteeId = u128;
+
+/// A cross-chain transfer request, as emitted by the `CrosschainTransferRequest` event on either chain.
+///
+/// This is the single source of truth for how a request is serialized before being hashed:
+/// both `most` contracts and the relayer build the request hash from here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct CrosschainRequest {
+    pub committee_id: CommitteeId,
+    pub dest_token_address: [u8; 32],
+    pub amount: u128,
+    pub dest_receiver_address: [u8; 32],
+    pub request_nonce: u128,
+}
+
+impl CrosschainRequest {
+    /// Encoding checked by the `most` contract on Aleph Zero.
+    ///
+    /// Integers are little-endian and take 16 bytes each, addresses are appended as they are.
+    pub fn azero_encoding(&self) -> Vec<u8> {
+        concat_u8_arrays(vec![
+            &self.committee_id.to_le_bytes(),
+            &self.dest_token_address,
+            &self.amount.to_le_bytes(),
+            &self.dest_receiver_address,
+            &self.request_nonce.to_le_bytes(),
+        ])
+    }
+
+    /// Encoding checked by `Most.sol`, i.e. `abi.encodePacked(uint256, bytes32, uint256, bytes32, uint256)`.
+    ///
+    /// Integers are left-padded to 32 bytes and big-endian.
+    pub fn eth_encoding(&self) -> Vec<u8> {
+        concat_u8_arrays(vec![
+            &u256_be_bytes(self.committee_id),
+            &self.dest_token_address,
+            &u256_be_bytes(self.amount),
+            &self.dest_receiver_address,
+            &u256_be_bytes(self.request_nonce),
+        ])
+    }
+
+    /// Hash under which the request is voted on when Aleph Zero is the destination chain.
+    pub fn azero_hash(&self) -> Keccak256HashOutput {
+        keccak256(&self.azero_encoding())
+    }
+
+    /// Hash under which the request is voted on when Ethereum is the destination chain.
+    pub fn eth_hash(&self) -> Keccak256HashOutput {
+        keccak256(&self.eth_encoding())
+    }
+}
+
+fn u256_be_bytes(value: u128) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[16..].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Golden vectors. The Ethereum one matches `ethers.solidityPackedKeccak256` in `eth/test/Most.js`.
+    const REQUEST: CrosschainRequest = CrosschainRequest {
+        committee_id: 1,
+        dest_token_address: [0x1; 32],
+        amount: 1000,
+        dest_receiver_address: [0x2; 32],
+        request_nonce: 7,
+    };
+    const AZERO_HASH: &str = "f8363b8e316db85a7ebbcf7ad9c3e9e4d6eb07dd374e92728795c7c6a35ecda9";
+    const ETH_HASH: &str = "2bdc0a4db5538f345c19f99f3b5fd5a653d23f2b06aa98ccd82a5757e58e1790";
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn azero_encoding_is_little_endian_concatenation() {
+        let encoding = REQUEST.azero_encoding();
+
+        assert_eq!(encoding.len(), 16 + 32 + 16 + 32 + 16);
+        assert_eq!(encoding[..16], 1u128.to_le_bytes());
+        assert_eq!(encoding[16..48], [0x1; 32]);
+        assert_eq!(encoding[48..64], 1000u128.to_le_bytes());
+        assert_eq!(encoding[64..96], [0x2; 32]);
+        assert_eq!(encoding[96..], 7u128.to_le_bytes());
+    }
+
+    #[test]
+    fn eth_encoding_is_abi_encode_packed() {
+        let encoding = REQUEST.eth_encoding();
+
+        assert_eq!(encoding.len(), 5 * 32);
+        assert_eq!(encoding[..31], [0; 31]);
+        assert_eq!(encoding[31], 1);
+        assert_eq!(encoding[32..64], [0x1; 32]);
+        assert_eq!(encoding[64..96], u256_be_bytes(1000));
+        assert_eq!(encoding[96..128], [0x2; 32]);
+        assert_eq!(encoding[128..], u256_be_bytes(7));
+    }
+
+    #[test]
+    fn azero_hash_matches_golden_vector() {
+        assert_eq!(to_hex(&REQUEST.azero_hash()), AZERO_HASH);
+    }
+
+    #[test]
+    fn eth_hash_matches_golden_vector() {
+        assert_eq!(to_hex(&REQUEST.eth_hash()), ETH_HASH);
+    }
+
+    #[test]
+    fn scale_roundtrip() {
+        let encoded = REQUEST.encode();
+        assert_eq!(
+            CrosschainRequest::decode(&mut &encoded[..]).expect("valid encoding"),
+            REQUEST
+        );
+    }
+}
@@ -34,7 +34,7 @@ mod e2e {
     };
     use psp22::{PSP22Error, PSP22};
     use scale::{Decode, Encode};
-    use shared::{keccak256, Keccak256HashOutput};
+    use shared::{CommitteeId, CrosschainRequest, Keccak256HashOutput};
     use test_oracle::TestOracleRef;
     use wrapped_token::TokenRef;
 
@@ -42,8 +42,6 @@ mod e2e {
         filter_decode_events_as, get_contract_emitted_events, ContractEmitted, EventWithTopics,
     };
 
-    type CommitteeId = u128;
-
     const TOKEN_INITIAL_SUPPLY: u128 = 10000;
     const DEFAULT_THRESHOLD: u128 = 3;
     const DECIMALS: u8 = 8;
@@ -882,15 +880,14 @@ mod e2e {
         receiver_address: AccountId,
         request_nonce: u128,
     ) -> Keccak256HashOutput {
-        let request_data = [
-            &commitee_id.to_le_bytes(),
-            AsRef::<[u8]>::as_ref(&token_address),
-            &amount.to_le_bytes(),
-            AsRef::<[u8]>::as_ref(&receiver_address),
-            &request_nonce.to_le_bytes(),
-        ]
-        .concat();
-        keccak256(&request_data)
+        CrosschainRequest {
+            committee_id: commitee_id,
+            dest_token_address: *AsRef::<[u8; 32]>::as_ref(&token_address),
+            amount,
+            dest_receiver_address: *AsRef::<[u8; 32]>::as_ref(&receiver_address),
+            request_nonce,
+        }
+        .azero_hash()
     }
 
     #[derive(Debug)]
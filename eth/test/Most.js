@@ -119,6 +119,18 @@ describe("Most", function () {
   });
 
   describe("receiveRequest", function () {
+    it("Request hash matches the golden vector shared with the relayer", async () => {
+      // Same request as in `azero/contracts/shared/src/request.rs`
+      const requestHash = ethers.solidityPackedKeccak256(
+        ["uint256", "bytes32", "uint256", "bytes32", "uint256"],
+        [1, "0x" + "01".repeat(32), 1000, "0x" + "02".repeat(32), 7],
+      );
+
+      expect(requestHash).to.equal(
+        "0x2bdc0a4db5538f345c19f99f3b5fd5a653d23f2b06aa98ccd82a5757e58e1790",
+      );
+    });
+
     it("Reverts if caller is not a guardian", async () => {
       const { most, tokenAddressBytes32 } = await loadFixture(
         deployEightGuardianMostFixture,
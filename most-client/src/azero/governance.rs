@@ -0,0 +1,96 @@
+use aleph_client::{AccountId, ConnectionApi, SignedConnection, TxInfo};
+pub use governance::{GovernanceError, Proposal};
+
+use super::{account_id_bytes, selector, AzeroContractError, Contract, WeightLimits};
+
+/// Client of the `governance` contract on Aleph Zero, which owns `most` and the tokens.
+pub struct GovernanceInstance {
+    contract: Contract,
+}
+
+impl GovernanceInstance {
+    pub fn new(address: &str, limits: WeightLimits) -> Result<Self, AzeroContractError> {
+        Ok(Self {
+            contract: Contract::new(address, limits)?,
+        })
+    }
+
+    pub fn address(&self) -> &AccountId {
+        &self.contract.address
+    }
+
+    /// Proposes to call `message` on `destination` with SCALE encoded `args`, and votes for it.
+    pub async fn submit_proposal(
+        &self,
+        signed_connection: &SignedConnection,
+        destination: &AccountId,
+        message: &str,
+        args: Vec<u8>,
+        allow_reentry: bool,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<GovernanceError>(
+                signed_connection,
+                "submit_proposal",
+                (
+                    account_id_bytes(destination),
+                    selector(message),
+                    args,
+                    allow_reentry,
+                ),
+                0,
+            )
+            .await
+    }
+
+    pub async fn vote(
+        &self,
+        signed_connection: &SignedConnection,
+        proposal_id: u128,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<GovernanceError>(signed_connection, "vote", proposal_id, 0)
+            .await
+    }
+
+    /// Executes a proposal that has reached the quorum. Anyone can do it.
+    pub async fn execute_proposal(
+        &self,
+        signed_connection: &SignedConnection,
+        proposal_id: u128,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<GovernanceError>(signed_connection, "execute_proposal", proposal_id, 0)
+            .await
+    }
+
+    pub async fn is_member<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        account: &AccountId,
+    ) -> Result<bool, AzeroContractError> {
+        self.contract
+            .read(connection, "is_member", account_id_bytes(account))
+            .await
+    }
+
+    pub async fn has_quorum<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        proposal_id: u128,
+    ) -> Result<bool, AzeroContractError> {
+        self.contract
+            .try_read::<_, _, GovernanceError>(connection, "has_quorum", proposal_id)
+            .await
+    }
+
+    pub async fn get_signature_count<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        proposal_id: u128,
+    ) -> Result<u32, AzeroContractError> {
+        self.contract
+            .try_read::<_, _, GovernanceError>(connection, "get_signature_count", proposal_id)
+            .await
+    }
+}
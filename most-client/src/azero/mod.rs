@@ -0,0 +1,319 @@
+use std::{fmt::Debug, str::FromStr};
+
+use aleph_client::{
+    api,
+    pallets::contract::{ContractCallArgs, ContractRpc},
+    sp_weights::weight_v2::Weight,
+    AccountId, AlephConfig, AsConnection, Balance, ConnectionApi, SignedConnection,
+    SignedConnectionApi, TxInfo,
+};
+use codec::{Decode, DecodeAll, Encode};
+use ink::{
+    env::{hash::Blake2x256, hash_bytes},
+    primitives::LangError,
+};
+use subxt::{tx::TxProgress, utils::H256, OnlineClient};
+use thiserror::Error;
+
+mod governance;
+mod most;
+mod oracle;
+mod token;
+
+pub use self::{
+    governance::{GovernanceError, GovernanceInstance, Proposal},
+    most::{
+        is_retriable_vote_error, AzeroMostEvent, CrosschainTransferRequest, MostError,
+        MostInstance, RequestProcessed, RequestSigned, SignedProcessedRequest, VoteBatch,
+    },
+    oracle::{OracleError, OracleInstance},
+    token::TokenInstance,
+};
+
+#[derive(Debug, Error)]
+#[error(transparent)]
+#[non_exhaustive]
+pub enum AzeroContractError {
+    #[error("aleph-client error")]
+    AlephClient(#[from] anyhow::Error),
+
+    #[error("subxt error")]
+    Subxt(#[from] subxt::Error),
+
+    #[error("not account id")]
+    NotAccountId(String),
+
+    #[error("cannot decode contract data")]
+    Decode(#[from] codec::Error),
+
+    #[error("Missing or invalid field")]
+    MissingOrInvalidField(String),
+
+    #[error("contract returned an error")]
+    ContractError(String),
+
+    #[error("most contract returned an error")]
+    Most(MostError),
+
+    #[error("required weight exceeds the limit")]
+    WeightLimitExceeded(String),
+}
+
+impl AzeroContractError {
+    /// Whether retrying cannot help, i.e. the error comes from our config or from the contract interface.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(
+            self,
+            Self::AlephClient(_)
+                | Self::Subxt(_)
+                | Self::ContractError(_)
+                | Self::Most(_)
+                | Self::WeightLimitExceeded(_)
+        )
+    }
+}
+
+/// A contract call submitted to the transaction pool, which may still be dropped before it is included in a block.
+pub struct SubmittedTx {
+    progress: TxProgress<AlephConfig, OnlineClient<AlephConfig>>,
+}
+
+impl SubmittedTx {
+    pub fn tx_hash(&self) -> H256 {
+        self.progress.extrinsic_hash()
+    }
+
+    /// Waits for the transaction to be included in a block and to succeed there.
+    pub async fn wait_for_in_block(self) -> Result<TxInfo, AzeroContractError> {
+        let events = self
+            .progress
+            .wait_for_in_block()
+            .await?
+            .wait_for_success()
+            .await?;
+        Ok(TxInfo {
+            block_hash: events.block_hash(),
+            tx_hash: events.extrinsic_hash(),
+        })
+    }
+}
+
+/// Limits of the weight reserved for transactions, which is estimated with a dry-run.
+#[derive(Debug, Clone, Copy)]
+pub struct WeightLimits {
+    pub ref_time_limit: u64,
+    pub proof_size_limit: u64,
+    /// Added to the estimated weight, as the state can change before the transaction is executed
+    pub gas_safety_margin_percent: u64,
+}
+
+// An ink! contract called with SCALE encoded messages, so that no metadata is needed.
+struct Contract {
+    address: AccountId,
+    limits: WeightLimits,
+}
+
+impl Contract {
+    fn new(address: &str, limits: WeightLimits) -> Result<Self, AzeroContractError> {
+        let address = AccountId::from_str(address)
+            .map_err(|why| AzeroContractError::NotAccountId(why.to_string()))?;
+        Ok(Self { address, limits })
+    }
+
+    // Calls a message that only reads the contract state and decodes what it returns.
+    async fn read<C: ConnectionApi, T: Decode>(
+        &self,
+        connection: &C,
+        message: &str,
+        args: impl Encode,
+    ) -> Result<T, AzeroContractError> {
+        let result = connection
+            .call_and_get(ContractCallArgs {
+                origin: self.address.clone(),
+                dest: self.address.clone(),
+                value: 0,
+                gas_limit: None,
+                storage_deposit_limit: None,
+                input_data: encode_message(message, args),
+            })
+            .await
+            .map_err(AzeroContractError::AlephClient)?;
+
+        let return_value = result
+            .result
+            .map_err(|why| AzeroContractError::ContractError(format!("{why:?}")))?;
+        decode_return(&return_value.data)
+    }
+
+    // Reads a message returning `Result<T, E>` and flattens its error.
+    async fn try_read<C: ConnectionApi, T: Decode, E: Decode + Debug>(
+        &self,
+        connection: &C,
+        message: &str,
+        args: impl Encode,
+    ) -> Result<T, AzeroContractError> {
+        let result: Result<T, E> = self.read(connection, message, args).await?;
+        result.map_err(|why| AzeroContractError::ContractError(format!("{why:?}")))
+    }
+
+    // Sends a message returning `Result<_, E>` once a dry-run succeeds, with the weight it needs. Returns as soon as the
+    // transaction is included in a block.
+    async fn exec<E: Decode + Debug>(
+        &self,
+        signed_connection: &SignedConnection,
+        message: &str,
+        args: impl Encode,
+        value: Balance,
+    ) -> Result<TxInfo, AzeroContractError> {
+        let data = encode_message(message, args);
+        let (weight, _) = self
+            .dry_run::<E>(signed_connection, message, data.clone(), value)
+            .await?;
+        self.submit(signed_connection, data, weight, value)
+            .await?
+            .wait_for_in_block()
+            .await
+    }
+
+    // Submits a message call with the given weight. Returns as soon as the transaction is in the pool of the node.
+    async fn submit(
+        &self,
+        signed_connection: &SignedConnection,
+        data: Vec<u8>,
+        weight: Weight,
+        value: Balance,
+    ) -> Result<SubmittedTx, AzeroContractError> {
+        let call =
+            api::tx()
+                .contracts()
+                .call(self.address.clone().into(), value, weight, None, data);
+        let progress = signed_connection
+            .as_connection()
+            .as_client()
+            .tx()
+            .sign_and_submit_then_watch_default(&call, signed_connection.signer())
+            .await?;
+        Ok(SubmittedTx { progress })
+    }
+
+    // Dry-runs a message as the signer and returns the weight to reserve for it with the data it returns, or the error
+    // it fails with. Messages revert when they return an error, which is decoded as `E`.
+    async fn dry_run<E: Decode + Debug>(
+        &self,
+        signed_connection: &SignedConnection,
+        message: &str,
+        data: Vec<u8>,
+        value: Balance,
+    ) -> Result<(Weight, Vec<u8>), AzeroContractError> {
+        let result = signed_connection
+            .call_and_get(ContractCallArgs {
+                origin: signed_connection.account_id().clone(),
+                dest: self.address.clone(),
+                value,
+                gas_limit: None,
+                storage_deposit_limit: None,
+                input_data: data,
+            })
+            .await
+            .map_err(AzeroContractError::AlephClient)?;
+
+        let return_value = result
+            .result
+            .map_err(|why| AzeroContractError::ContractError(format!("{why:?}")))?;
+        if return_value.did_revert() {
+            let data = &return_value.data;
+            let why = match <Result<Result<(), E>, LangError>>::decode_all(&mut &data[..]) {
+                Ok(Ok(Err(why))) => format!("{why:?}"),
+                Ok(Err(why)) => format!("{why:?}"),
+                _ => format!("0x{}", hex::encode(data)),
+            };
+            return Err(AzeroContractError::ContractError(format!(
+                "{message} reverted with {why}"
+            )));
+        }
+
+        let limits = &self.limits;
+        let with_margin = |weight: u64| {
+            weight.saturating_mul(100u64.saturating_add(limits.gas_safety_margin_percent)) / 100
+        };
+        let ref_time = with_margin(result.gas_required.ref_time());
+        let proof_size = with_margin(result.gas_required.proof_size());
+        if ref_time > limits.ref_time_limit || proof_size > limits.proof_size_limit {
+            return Err(AzeroContractError::WeightLimitExceeded(format!(
+                "{message} needs ref time {ref_time} and proof size {proof_size}, the limits are {} and {}",
+                limits.ref_time_limit, limits.proof_size_limit
+            )));
+        }
+
+        Ok((
+            Weight {
+                ref_time,
+                proof_size,
+            },
+            return_value.data,
+        ))
+    }
+}
+
+// ink! selector of a message, i.e. the first 4 bytes of the BLAKE2 hash of its name. Messages defined in a trait are
+// named `<trait>::<message>`.
+fn selector(message: &str) -> [u8; 4] {
+    let mut hash = [0u8; 32];
+    hash_bytes::<Blake2x256>(message.as_bytes(), &mut hash);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+// Input of a message call: its selector followed by the SCALE encoded arguments.
+fn encode_message(message: &str, args: impl Encode) -> Vec<u8> {
+    let mut data = selector(message).to_vec();
+    args.encode_to(&mut data);
+    data
+}
+
+// Messages return `Result<_, LangError>`, where the error means the call could not be dispatched.
+fn decode_return<T: Decode>(data: &[u8]) -> Result<T, AzeroContractError> {
+    let result: Result<T, LangError> = DecodeAll::decode_all(&mut &data[..])?;
+    result.map_err(|why| AzeroContractError::ContractError(format!("{why:?}")))
+}
+
+fn account_id_bytes(account: &AccountId) -> [u8; 32] {
+    account.clone().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selector_is_prefix_of_blake2_hash_of_message() {
+        assert_eq!(selector("receive_requests"), [0x4b, 0x86, 0xb3, 0x48]);
+        assert_eq!(selector("PSP22::balance_of"), [0x65, 0x68, 0x38, 0x2f]);
+    }
+
+    #[test]
+    fn encodes_arguments_after_selector() {
+        assert_eq!(
+            encode_message("receive_requests", ()),
+            selector("receive_requests")
+        );
+        let mut expected = selector("is_request_processed").to_vec();
+        expected.extend([7; 32]);
+        expected.extend(1u128.to_le_bytes());
+        assert_eq!(
+            encode_message("is_request_processed", ([7u8; 32], 1u128)),
+            expected
+        );
+    }
+
+    #[test]
+    fn decodes_return_value_and_dispatch_error() {
+        let returned = Ok::<u128, LangError>(5).encode();
+        assert_eq!(decode_return::<u128>(&returned).unwrap(), 5);
+
+        let not_dispatched = Err::<u128, LangError>(LangError::CouldNotReadInput).encode();
+        assert!(matches!(
+            decode_return::<u128>(&not_dispatched),
+            Err(AzeroContractError::ContractError(_))
+        ));
+    }
+}
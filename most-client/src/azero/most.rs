@@ -0,0 +1,531 @@
+use aleph_client::{
+    api::contracts::events::ContractEmitted, sp_weights::weight_v2::Weight, AccountId, AlephConfig,
+    AsConnection, Balance, ConnectionApi, SignedConnection, TxInfo,
+};
+use codec::DecodeAll;
+use futures::{stream::BoxStream, StreamExt};
+use log::{trace, warn};
+pub use most::{
+    most::{CrosschainTransferRequest, RequestProcessed, RequestSigned, SignedProcessedRequest},
+    MostError,
+};
+use shared::{event_signature_topic, CrosschainRequest};
+use subxt::events::{EventDetails, Events};
+
+use super::{
+    account_id_bytes, decode_return, encode_message, AzeroContractError, Contract, SubmittedTx,
+    WeightLimits,
+};
+
+/// Events of the `most` contract, decoded into the types defined by the contract itself.
+#[derive(Debug)]
+pub enum AzeroMostEvent {
+    CrosschainTransferRequest(CrosschainTransferRequest),
+    RequestProcessed(RequestProcessed),
+    RequestSigned(RequestSigned),
+    SignedProcessedRequest(SignedProcessedRequest),
+}
+
+const CROSSCHAIN_TRANSFER_REQUEST_TOPIC: [u8; 32] =
+    event_signature_topic("Most::CrosschainTransferRequest");
+const REQUEST_PROCESSED_TOPIC: [u8; 32] = event_signature_topic("Most::RequestProcessed");
+const REQUEST_SIGNED_TOPIC: [u8; 32] = event_signature_topic("Most::RequestSigned");
+const SIGNED_PROCESSED_REQUEST_TOPIC: [u8; 32] =
+    event_signature_topic("Most::SignedProcessedRequest");
+
+impl AzeroMostEvent {
+    // Decodes the data of an event identified by its first topic. Returns `None` for events we don't know.
+    fn decode(signature_topic: &[u8; 32], data: &[u8]) -> Result<Option<Self>, codec::Error> {
+        // The first byte is the index of the event in the contract, the topic already tells which one it is.
+        let mut data = data.get(1..).ok_or("Event data is empty")?;
+        Ok(Some(match *signature_topic {
+            CROSSCHAIN_TRANSFER_REQUEST_TOPIC => {
+                Self::CrosschainTransferRequest(DecodeAll::decode_all(&mut data)?)
+            }
+            REQUEST_PROCESSED_TOPIC => Self::RequestProcessed(DecodeAll::decode_all(&mut data)?),
+            REQUEST_SIGNED_TOPIC => Self::RequestSigned(DecodeAll::decode_all(&mut data)?),
+            SIGNED_PROCESSED_REQUEST_TOPIC => {
+                Self::SignedProcessedRequest(DecodeAll::decode_all(&mut data)?)
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
+
+/// Whether a vote the contract rejects with `why` can succeed when sent again. Errors caused by the request itself or
+/// by the committee will not go away, anything else (e.g. a failed token transfer) might.
+pub fn is_retriable_vote_error(why: &MostError) -> bool {
+    !matches!(
+        why,
+        MostError::NotInCommittee
+            | MostError::HashDoesNotMatchData
+            | MostError::RequestAlreadySigned
+            | MostError::RequestAlreadyProcessed
+            | MostError::UnsupportedPair
+    )
+}
+
+/// Votes on requests taken from the front of a list, dry-run by [`MostInstance::prepare_votes`] to be sent in one
+/// extrinsic with [`MostInstance::receive_requests`].
+#[derive(Debug)]
+pub struct VoteBatch {
+    /// Number of requests covered by the batch
+    pub size: usize,
+    /// Per request of the batch, in order: whether its vote is sent, or the error the contract rejects it with
+    pub outcomes: Vec<Result<(), AzeroContractError>>,
+    votes: Vec<([u8; 32], CrosschainRequest)>,
+    weight: Weight,
+}
+
+// Drops the votes in `voted`, given as indices into the batch, that are rejected in the dry-run `results` and records
+// why in `outcomes`. Returns whether any vote has been dropped.
+fn reject_votes(
+    voted: &mut Vec<usize>,
+    results: Vec<Result<(), MostError>>,
+    outcomes: &mut [Result<(), AzeroContractError>],
+) -> bool {
+    let mut rejected = false;
+    let mut results = results.into_iter();
+    voted.retain(|&index| match results.next() {
+        Some(Err(why)) => {
+            outcomes[index] = Err(AzeroContractError::Most(why));
+            rejected = true;
+            false
+        }
+        _ => true,
+    });
+    rejected
+}
+
+/// Client of the `most` contract on Aleph Zero.
+pub struct MostInstance {
+    contract: Contract,
+}
+
+impl MostInstance {
+    pub fn new(address: &str, limits: WeightLimits) -> Result<Self, AzeroContractError> {
+        Ok(Self {
+            contract: Contract::new(address, limits)?,
+        })
+    }
+
+    pub fn address(&self) -> &AccountId {
+        &self.contract.address
+    }
+
+    /// Burns `amount` of `src_token_address` tokens of the signer and requests a transfer to Ethereum. `base_fee` has to
+    /// cover the fee quoted by [`Self::get_base_fee`].
+    pub async fn send_request(
+        &self,
+        signed_connection: &SignedConnection,
+        src_token_address: [u8; 32],
+        amount: u128,
+        dest_receiver_address: [u8; 32],
+        base_fee: Balance,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<MostError>(
+                signed_connection,
+                "send_request",
+                (src_token_address, amount, dest_receiver_address),
+                base_fee,
+            )
+            .await
+    }
+
+    /// Votes on a single request as a guardian.
+    pub async fn receive_request(
+        &self,
+        signed_connection: &SignedConnection,
+        request_hash: [u8; 32],
+        request: CrosschainRequest,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<MostError>(
+                signed_connection,
+                "receive_request",
+                (
+                    request_hash,
+                    request.committee_id,
+                    request.dest_token_address,
+                    request.amount,
+                    request.dest_receiver_address,
+                    request.request_nonce,
+                ),
+                0,
+            )
+            .await
+    }
+
+    /// Dry-runs votes on as many requests from the front of `requests` as fit the weight limits in one extrinsic.
+    ///
+    /// The batch is halved until it fits, the limits are only an upper bound. Votes the contract rejects are left out
+    /// of the batch and the rest is dry-run again, so that the weight reserved for them is what they need on their own.
+    /// A vote that does not fit on its own makes a batch of one, rejected with `WeightLimitExceeded`.
+    pub async fn prepare_votes(
+        &self,
+        signed_connection: &SignedConnection,
+        requests: &[([u8; 32], CrosschainRequest)],
+    ) -> Result<VoteBatch, AzeroContractError> {
+        let mut size = requests.len();
+        let (mut weight, mut results) = loop {
+            match self
+                .dry_run_votes(signed_connection, &requests[..size])
+                .await
+            {
+                Err(AzeroContractError::WeightLimitExceeded(_)) if size > 1 => {
+                    size = (size + 1) / 2;
+                }
+                Err(why @ AzeroContractError::WeightLimitExceeded(_)) => {
+                    return Ok(VoteBatch {
+                        size,
+                        outcomes: vec![Err(why)],
+                        votes: Vec::new(),
+                        weight: Weight {
+                            ref_time: 0,
+                            proof_size: 0,
+                        },
+                    });
+                }
+                result => break result?,
+            }
+        };
+
+        let mut outcomes: Vec<_> = (0..size).map(|_| Ok(())).collect();
+        let mut voted: Vec<_> = (0..size).collect();
+        // The state can change between dry-runs, so this repeats until no vote is rejected.
+        while reject_votes(&mut voted, results, &mut outcomes) && !voted.is_empty() {
+            let votes: Vec<_> = voted.iter().map(|&index| requests[index]).collect();
+            (weight, results) = self.dry_run_votes(signed_connection, &votes).await?;
+        }
+
+        Ok(VoteBatch {
+            size,
+            outcomes,
+            votes: voted.iter().map(|&index| requests[index]).collect(),
+            weight,
+        })
+    }
+
+    /// Votes on the requests of a batch that have passed the dry-run, in one extrinsic. Returns as soon as it is
+    /// submitted, or `None` if there is no vote to send.
+    pub async fn receive_requests(
+        &self,
+        signed_connection: &SignedConnection,
+        batch: &VoteBatch,
+    ) -> Result<Option<SubmittedTx>, AzeroContractError> {
+        if batch.votes.is_empty() {
+            return Ok(None);
+        }
+
+        trace!("Voting on {} requests in one extrinsic", batch.votes.len());
+        self.contract
+            .submit(
+                signed_connection,
+                encode_message("receive_requests", &batch.votes),
+                batch.weight,
+                0,
+            )
+            .await
+            .map(Some)
+    }
+
+    // Dry-runs votes on all `requests` in one extrinsic. Returns the weight to reserve for it and the result of each
+    // vote, as the contract does not revert when only some of them fail.
+    async fn dry_run_votes(
+        &self,
+        signed_connection: &SignedConnection,
+        requests: &[([u8; 32], CrosschainRequest)],
+    ) -> Result<(Weight, Vec<Result<(), MostError>>), AzeroContractError> {
+        let data = encode_message("receive_requests", requests);
+        let (weight, return_data) = self
+            .contract
+            .dry_run::<MostError>(signed_connection, "receive_requests", data, 0)
+            .await?;
+
+        let results: Vec<Result<(), MostError>> = decode_return(&return_data)?;
+        if results.len() != requests.len() {
+            return Err(AzeroContractError::MissingOrInvalidField(format!(
+                "receive_requests returned {} results for {} requests",
+                results.len(),
+                requests.len()
+            )));
+        }
+        Ok((weight, results))
+    }
+
+    /// Pays out the rewards `member` has earned in the committee so far.
+    pub async fn payout_rewards(
+        &self,
+        signed_connection: &SignedConnection,
+        committee_id: u128,
+        member: &AccountId,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<MostError>(
+                signed_connection,
+                "payout_rewards",
+                (committee_id, account_id_bytes(member)),
+                0,
+            )
+            .await
+    }
+
+    /// Fee in AZERO to be paid with [`Self::send_request`], based on the current Ethereum gas price.
+    pub async fn get_base_fee<C: ConnectionApi>(
+        &self,
+        connection: &C,
+    ) -> Result<Balance, AzeroContractError> {
+        self.contract
+            .try_read::<_, _, MostError>(connection, "get_base_fee", ())
+            .await
+    }
+
+    pub async fn get_collected_committee_rewards<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        committee_id: u128,
+    ) -> Result<u128, AzeroContractError> {
+        self.contract
+            .read(connection, "get_collected_committee_rewards", committee_id)
+            .await
+    }
+
+    pub async fn get_paid_out_member_rewards<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        committee_id: u128,
+        member: &AccountId,
+    ) -> Result<u128, AzeroContractError> {
+        self.contract
+            .read(
+                connection,
+                "get_paid_out_member_rewards",
+                (committee_id, account_id_bytes(member)),
+            )
+            .await
+    }
+
+    pub async fn get_outstanding_member_rewards<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        committee_id: u128,
+        member: &AccountId,
+    ) -> Result<u128, AzeroContractError> {
+        self.contract
+            .try_read::<_, _, MostError>(
+                connection,
+                "get_outstanding_member_rewards",
+                (committee_id, account_id_bytes(member)),
+            )
+            .await
+    }
+
+    pub async fn is_in_committee<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        committee_id: u128,
+        account: &AccountId,
+    ) -> Result<bool, AzeroContractError> {
+        self.contract
+            .read(
+                connection,
+                "is_in_committee",
+                (committee_id, account_id_bytes(account)),
+            )
+            .await
+    }
+
+    pub async fn is_request_processed<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        request_hash: [u8; 32],
+    ) -> Result<bool, AzeroContractError> {
+        self.contract
+            .read(connection, "is_request_processed", request_hash)
+            .await
+    }
+
+    pub async fn has_signed_request<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        request_hash: [u8; 32],
+        account: &AccountId,
+    ) -> Result<bool, AzeroContractError> {
+        self.contract
+            .read(
+                connection,
+                "has_signed_request",
+                (request_hash, account_id_bytes(account)),
+            )
+            .await
+    }
+
+    pub async fn get_current_committee_id<C: ConnectionApi>(
+        &self,
+        connection: &C,
+    ) -> Result<u128, AzeroContractError> {
+        self.contract
+            .try_read::<_, _, MostError>(connection, "get_current_committee_id", ())
+            .await
+    }
+
+    /// Decodes the events emitted by this contract. Events that cannot be decoded are logged and skipped.
+    pub fn filter_events(&self, events: Events<AlephConfig>) -> Vec<AzeroMostEvent> {
+        events
+            .iter()
+            .filter_map(|event| match self.decode_event(event) {
+                Ok(event) => event,
+                Err(why) => {
+                    warn!("Failed to decode event: {why:?}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Events of this contract from every newly finalized block, together with the block number.
+    ///
+    /// Blocks finalized while the stream is not polled are not skipped, but nothing is replayed after it ends, so
+    /// callers that must not miss events have to track the last block themselves.
+    pub async fn event_stream<C: AsConnection>(
+        &self,
+        connection: &C,
+    ) -> Result<
+        BoxStream<'_, Result<(u32, Vec<AzeroMostEvent>), AzeroContractError>>,
+        AzeroContractError,
+    > {
+        let blocks = connection
+            .as_connection()
+            .as_client()
+            .blocks()
+            .subscribe_finalized()
+            .await?;
+
+        Ok(blocks
+            .then(move |block| async move {
+                let block = block?;
+                let events = block.events().await?;
+                Ok((block.number(), self.filter_events(events)))
+            })
+            .boxed())
+    }
+
+    fn decode_event(
+        &self,
+        event: Result<EventDetails<AlephConfig>, subxt::Error>,
+    ) -> Result<Option<AzeroMostEvent>, AzeroContractError> {
+        let event = event?;
+        let Some(ContractEmitted { contract, data }) = event.as_event::<ContractEmitted>()? else {
+            return Ok(None);
+        };
+        if contract.0 != account_id_bytes(self.address()) {
+            return Ok(None);
+        }
+
+        let Some(signature_topic) = event.topics().first() else {
+            trace!("Skipping an anonymous event");
+            return Ok(None);
+        };
+        Ok(AzeroMostEvent::decode(&signature_topic.0, &data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codec::Encode;
+
+    use super::*;
+
+    fn event_data(index: u8, event: impl Encode) -> Vec<u8> {
+        let mut data = vec![index];
+        event.encode_to(&mut data);
+        data
+    }
+
+    #[test]
+    fn decodes_event_by_signature_topic() {
+        let event = RequestSigned {
+            request_hash: [1; 32],
+            signer: [2; 32].into(),
+        };
+        let data = event_data(2, &event);
+
+        match AzeroMostEvent::decode(&REQUEST_SIGNED_TOPIC, &data) {
+            Ok(Some(AzeroMostEvent::RequestSigned(decoded))) => assert_eq!(decoded, event),
+            other => panic!("Unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn skips_unknown_events() {
+        let data = event_data(0, [1u8; 32]);
+
+        assert!(matches!(AzeroMostEvent::decode(&[0; 32], &data), Ok(None)));
+    }
+
+    #[test]
+    fn rejects_malformed_event_data() {
+        let request_processed = RequestProcessed {
+            request_hash: [1; 32],
+            dest_token_address: [2; 32],
+        };
+        let data = event_data(1, &request_processed);
+
+        assert!(AzeroMostEvent::decode(&REQUEST_PROCESSED_TOPIC, &[]).is_err());
+        assert!(AzeroMostEvent::decode(&REQUEST_PROCESSED_TOPIC, &data[..data.len() - 1]).is_err());
+        assert!(
+            AzeroMostEvent::decode(&REQUEST_PROCESSED_TOPIC, &[data, vec![0]].concat()).is_err()
+        );
+    }
+
+    #[test]
+    fn rejected_votes_are_left_out_of_batch() {
+        let mut voted = vec![0, 1, 2, 3];
+        let mut outcomes: Vec<_> = (0..4).map(|_| Ok(())).collect();
+
+        assert!(reject_votes(
+            &mut voted,
+            vec![
+                Ok(()),
+                Err(MostError::RequestAlreadySigned),
+                Ok(()),
+                Err(MostError::NotInCommittee)
+            ],
+            &mut outcomes,
+        ));
+        assert_eq!(voted, vec![0, 2]);
+
+        // Results of a dry-run of the remaining votes refer to them only.
+        assert!(reject_votes(
+            &mut voted,
+            vec![Err(MostError::Arithmetic), Ok(())],
+            &mut outcomes
+        ));
+        assert_eq!(voted, vec![2]);
+        assert!(!reject_votes(&mut voted, vec![Ok(())], &mut outcomes));
+        assert_eq!(voted, vec![2]);
+
+        assert!(matches!(
+            &outcomes[..],
+            [
+                Err(AzeroContractError::Most(MostError::Arithmetic)),
+                Err(AzeroContractError::Most(MostError::RequestAlreadySigned)),
+                Ok(()),
+                Err(AzeroContractError::Most(MostError::NotInCommittee)),
+            ]
+        ));
+    }
+
+    #[test]
+    fn only_votes_rejected_for_the_request_itself_are_not_retried() {
+        assert!(!is_retriable_vote_error(&MostError::HashDoesNotMatchData));
+        assert!(!is_retriable_vote_error(
+            &MostError::RequestAlreadyProcessed
+        ));
+        assert!(is_retriable_vote_error(&MostError::Arithmetic));
+        assert!(is_retriable_vote_error(&MostError::InkEnvError(
+            "TransferFailed".to_string()
+        )));
+    }
+}
@@ -0,0 +1,43 @@
+use aleph_client::{AccountId, ConnectionApi, SignedConnection, TxInfo};
+pub use oracle::oracle::OracleError;
+
+use super::{AzeroContractError, Contract, WeightLimits};
+
+/// Client of the Ethereum gas price oracle on Aleph Zero, which `most` uses to quote its base fee.
+pub struct OracleInstance {
+    contract: Contract,
+}
+
+impl OracleInstance {
+    pub fn new(address: &str, limits: WeightLimits) -> Result<Self, AzeroContractError> {
+        Ok(Self {
+            contract: Contract::new(address, limits)?,
+        })
+    }
+
+    pub fn address(&self) -> &AccountId {
+        &self.contract.address
+    }
+
+    /// Price of one unit of Ethereum gas in picoAZERO, with the time of its last update in milliseconds since the UNIX
+    /// epoch.
+    pub async fn get_price<C: ConnectionApi>(
+        &self,
+        connection: &C,
+    ) -> Result<(u128, u64), AzeroContractError> {
+        self.contract
+            .read(connection, "EthGasPriceOracle::get_price", ())
+            .await
+    }
+
+    /// Sets the price, only the owner of the oracle can do it.
+    pub async fn update_price(
+        &self,
+        signed_connection: &SignedConnection,
+        new_price: u128,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<OracleError>(signed_connection, "update_price", new_price, 0)
+            .await
+    }
+}
@@ -0,0 +1,88 @@
+use aleph_client::{AccountId, Balance, ConnectionApi, SignedConnection, TxInfo};
+use psp22::PSP22Error;
+
+use super::{account_id_bytes, AzeroContractError, Contract, WeightLimits};
+
+/// Client of a PSP22 token on Aleph Zero, e.g. one of the wrapped tokens minted by `most`.
+pub struct TokenInstance {
+    contract: Contract,
+}
+
+impl TokenInstance {
+    pub fn new(address: &str, limits: WeightLimits) -> Result<Self, AzeroContractError> {
+        Ok(Self {
+            contract: Contract::new(address, limits)?,
+        })
+    }
+
+    pub fn address(&self) -> &AccountId {
+        &self.contract.address
+    }
+
+    pub async fn total_supply<C: ConnectionApi>(
+        &self,
+        connection: &C,
+    ) -> Result<Balance, AzeroContractError> {
+        self.contract
+            .read(connection, "PSP22::total_supply", ())
+            .await
+    }
+
+    pub async fn balance_of<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        owner: &AccountId,
+    ) -> Result<Balance, AzeroContractError> {
+        self.contract
+            .read(connection, "PSP22::balance_of", account_id_bytes(owner))
+            .await
+    }
+
+    pub async fn allowance<C: ConnectionApi>(
+        &self,
+        connection: &C,
+        owner: &AccountId,
+        spender: &AccountId,
+    ) -> Result<Balance, AzeroContractError> {
+        self.contract
+            .read(
+                connection,
+                "PSP22::allowance",
+                (account_id_bytes(owner), account_id_bytes(spender)),
+            )
+            .await
+    }
+
+    /// Allows `spender`, e.g. the `most` contract, to burn up to `value` tokens of the signer.
+    pub async fn approve(
+        &self,
+        signed_connection: &SignedConnection,
+        spender: &AccountId,
+        value: Balance,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<PSP22Error>(
+                signed_connection,
+                "PSP22::approve",
+                (account_id_bytes(spender), value),
+                0,
+            )
+            .await
+    }
+
+    pub async fn transfer(
+        &self,
+        signed_connection: &SignedConnection,
+        to: &AccountId,
+        value: Balance,
+    ) -> Result<TxInfo, AzeroContractError> {
+        self.contract
+            .exec::<PSP22Error>(
+                signed_connection,
+                "PSP22::transfer",
+                (account_id_bytes(to), value, Vec::<u8>::new()),
+                0,
+            )
+            .await
+    }
+}
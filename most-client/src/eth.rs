@@ -0,0 +1,155 @@
+use ethers::{
+    contract::{abigen, ContractError},
+    providers::Middleware,
+    types::U256,
+};
+use shared::CrosschainRequest;
+use thiserror::Error;
+
+abigen!(Most, "../eth/artifacts/contracts/Most.sol/Most.json");
+
+/// A field of a `CrosschainTransferRequest` event that does not fit into the `u128` used on Aleph Zero.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{field} {value} does not fit into u128")]
+pub struct RequestFieldOverflow {
+    pub field: &'static str,
+    pub value: U256,
+}
+
+impl TryFrom<&CrosschainTransferRequestFilter> for CrosschainRequest {
+    type Error = RequestFieldOverflow;
+
+    fn try_from(event: &CrosschainTransferRequestFilter) -> Result<Self, Self::Error> {
+        let to_u128 = |field, value: U256| {
+            u128::try_from(value).map_err(|_| RequestFieldOverflow { field, value })
+        };
+        Ok(Self {
+            committee_id: to_u128("committee_id", event.committee_id)?,
+            dest_token_address: event.dest_token_address,
+            amount: to_u128("amount", event.amount)?,
+            dest_receiver_address: event.dest_receiver_address,
+            request_nonce: to_u128("request_nonce", event.request_nonce)?,
+        })
+    }
+}
+
+/// Decoded reason of a reverted `Most.receiveRequest` call.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MostRevert {
+    #[error("not a member of the guardian committee")]
+    NotInCommittee,
+
+    #[error("request hash does not match the data")]
+    HashMismatch,
+
+    #[error("guardian has already signed the request")]
+    AlreadySigned,
+
+    #[error("reverted with reason: {0}")]
+    Other(String),
+
+    #[error("reverted without a reason")]
+    Unknown,
+}
+
+impl MostRevert {
+    pub fn from_reason(reason: &str) -> Self {
+        match reason {
+            "Not a member of the guardian committee" => Self::NotInCommittee,
+            "Hash does not match the data" => Self::HashMismatch,
+            "This guardian has already signed this request" => Self::AlreadySigned,
+            _ => Self::Other(reason.to_owned()),
+        }
+    }
+
+    /// Decodes the `Error(string)` revert data of a failed call, if that is why it failed.
+    pub fn from_contract_error<M: Middleware>(error: &ContractError<M>) -> Option<Self> {
+        if !error.is_revert() {
+            return None;
+        }
+        Some(match error.decode_revert::<String>() {
+            Some(reason) => Self::from_reason(&reason),
+            None => Self::Unknown,
+        })
+    }
+
+    /// Whether sending the same vote again can succeed. Reverts caused by the request itself or by the committee
+    /// will not go away, anything else (e.g. running out of gas or a failed token transfer) might.
+    pub fn is_retriable(&self) -> bool {
+        matches!(self, Self::Other(_) | Self::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer_event(amount: U256) -> CrosschainTransferRequestFilter {
+        CrosschainTransferRequestFilter {
+            committee_id: 1.into(),
+            dest_token_address: [2; 32],
+            amount,
+            dest_receiver_address: [3; 32],
+            request_nonce: 4.into(),
+        }
+    }
+
+    #[test]
+    fn converts_transfer_event_into_request() {
+        let request = CrosschainRequest::try_from(&transfer_event(u128::MAX.into()));
+
+        assert_eq!(
+            request,
+            Ok(CrosschainRequest {
+                committee_id: 1,
+                dest_token_address: [2; 32],
+                amount: u128::MAX,
+                dest_receiver_address: [3; 32],
+                request_nonce: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_values_above_u128() {
+        let amount = U256::from(u128::MAX) + 1;
+
+        assert_eq!(
+            CrosschainRequest::try_from(&transfer_event(amount)),
+            Err(RequestFieldOverflow {
+                field: "amount",
+                value: amount,
+            })
+        );
+    }
+
+    #[test]
+    fn maps_contract_reasons_to_reverts() {
+        // Reasons of the `require`s in `receiveRequest` of `eth/contracts/Most.sol`.
+        assert_eq!(
+            MostRevert::from_reason("Not a member of the guardian committee"),
+            MostRevert::NotInCommittee
+        );
+        assert_eq!(
+            MostRevert::from_reason("Hash does not match the data"),
+            MostRevert::HashMismatch
+        );
+        assert_eq!(
+            MostRevert::from_reason("This guardian has already signed this request"),
+            MostRevert::AlreadySigned
+        );
+        assert_eq!(
+            MostRevert::from_reason("Transfer failed"),
+            MostRevert::Other("Transfer failed".to_owned())
+        );
+    }
+
+    #[test]
+    fn retries_only_reverts_not_caused_by_request() {
+        assert!(!MostRevert::NotInCommittee.is_retriable());
+        assert!(!MostRevert::HashMismatch.is_retriable());
+        assert!(!MostRevert::AlreadySigned.is_retriable());
+        assert!(MostRevert::Other("Transfer failed".to_owned()).is_retriable());
+        assert!(MostRevert::Unknown.is_retriable());
+    }
+}
@@ -0,0 +1,10 @@
+//! Typed clients of the Most contracts on Aleph Zero and Ethereum.
+//!
+//! The Aleph Zero clients call the ink! contracts with SCALE encoded messages and decode what they return with the
+//! types of the contracts themselves, so no metadata is needed. The Ethereum client is generated from the ABI of
+//! `Most.sol`, which has to be compiled first.
+
+pub mod azero;
+pub mod eth;
+
+pub use shared::CrosschainRequest;
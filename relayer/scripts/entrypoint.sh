@@ -21,26 +21,59 @@ function get_address {
 ETH_NETWORK=${ETH_NETWORK:-"http://127.0.0.1:8545"}
 AZERO_NETWORK=${AZERO_NETWORK:-"ws://127.0.0.1:9944"}
 REDIS=${REDIS:-"redis://127.0.0.1:6379"}
+CHECKPOINT_STORE=${CHECKPOINT_STORE:-"redis"}
+CHECKPOINT_PATH=${CHECKPOINT_PATH:-"/var/lib/most-relayer/checkpoints"}
 
 KEYSTORE_PATH=${KEYSTORE_PATH:-""}
+AZERO_KEYSTORE_PATH=${AZERO_KEYSTORE_PATH:-""}
+AZERO_KEYSTORE_PASSWORD_FILE=${AZERO_KEYSTORE_PASSWORD_FILE:-""}
+AZERO_SEED_FILE=${AZERO_SEED_FILE:-""}
 RELAYER_ID=${RELAYER_ID:-0}
 
-AZERO_MOST_METADATA=${AZERO_MOST_METADATA:-"/usr/local/most.json"}
-
 ARGS=(
   --name "guardian_${RELAYER_ID}"
   --azero-contract-address=$(get_address $AZERO_ADDRESSES_FILE most)
   --eth-contract-address=$(get_address $ETH_ADDRESSES_FILE most)
-  --eth-node-http-url=${ETH_NETWORK}
-  --azero-node-wss-url=${AZERO_NETWORK}
+  --eth-node-http-urls=${ETH_NETWORK}
+  --azero-node-wss-urls=${AZERO_NETWORK}
   --dev-account-index=${RELAYER_ID}
   --redis-node=${REDIS}
+  --checkpoint-store=${CHECKPOINT_STORE}
+  --checkpoint-path=${CHECKPOINT_PATH}
   --rust-log=info
-  --azero-contract-metadata=${AZERO_MOST_METADATA}
 )
 
 if [[ -n "${KEYSTORE_PATH}" ]]; then
-  ARGS+=(--keystore-path=${KEYSTORE_PATH})
+  ARGS+=(--eth-keystore-path=${KEYSTORE_PATH})
+fi
+
+if [[ -n "${AZERO_KEYSTORE_PATH}" ]]; then
+  ARGS+=(--azero-keystore-path=${AZERO_KEYSTORE_PATH})
+  ARGS+=(--azero-keystore-password-file=${AZERO_KEYSTORE_PASSWORD_FILE})
+fi
+
+if [[ -n "${AZERO_SEED_FILE}" ]]; then
+  ARGS+=(--azero-seed-file=${AZERO_SEED_FILE})
+fi
+
+if [[ -n "${ETH_WSS_NETWORK}" ]]; then
+  ARGS+=(--eth-node-wss-url=${ETH_WSS_NETWORK})
+fi
+
+if [[ -n "${ETH_RPC_QUORUM}" ]]; then
+  ARGS+=(--eth-rpc-quorum=${ETH_RPC_QUORUM})
+fi
+
+if [[ -n "${ETH_FINALITY}" ]]; then
+  ARGS+=(--eth-finality=${ETH_FINALITY})
+fi
+
+if [[ -n "${ETH_MAX_PRIORITY_FEE_PER_GAS}" ]]; then
+  ARGS+=(--eth-max-priority-fee-per-gas=${ETH_MAX_PRIORITY_FEE_PER_GAS})
+fi
+
+if [[ -n "${ETH_MAX_FEE_PER_GAS_CAP}" ]]; then
+  ARGS+=(--eth-max-fee-per-gas-cap=${ETH_MAX_FEE_PER_GAS_CAP})
 fi
 
 if [[ -n "${DEV_MODE}" ]]; then
@@ -30,7 +30,7 @@ cargo run -- --rust-log=info \
   --name "guardian_${RELAYER_ID}" \
   --azero-contract-address=$(get_address $AZERO_ADDRESSES_FILE most) \
   --eth-contract-address=$(get_address $ETH_ADDRESSES_FILE most) \
-  --eth-node-http-url=${ETH_NETWORK} \
-  --azero-node-wss-url=${AZERO_NETWORK} \
+  --eth-node-http-urls=${ETH_NETWORK} \
+  --azero-node-wss-urls=${AZERO_NETWORK} \
   --dev-account-index=${RELAYER_ID} \
   --dev
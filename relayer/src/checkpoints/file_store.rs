@@ -0,0 +1,61 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+
+use super::{CheckpointError, CheckpointStore};
+
+/// Keeps every key in its own plain text file under `<path>/<name>`.
+pub struct FileCheckpointStore {
+    dir: PathBuf,
+    // Serializes writes, so that concurrent updates of one key cannot interleave.
+    write_lock: Mutex<()>,
+}
+
+impl FileCheckpointStore {
+    pub async fn open(path: impl AsRef<Path>, name: String) -> Result<Self, CheckpointError> {
+        let dir = path.as_ref().join(name);
+        fs::create_dir_all(&dir).await?;
+        Ok(Self {
+            dir,
+            write_lock: Mutex::new(()),
+        })
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for FileCheckpointStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, CheckpointError> {
+        match fs::read_to_string(self.dir.join(key)).await {
+            Ok(content) => Ok(Some(content)),
+            Err(why) if why.kind() == ErrorKind::NotFound => Ok(None),
+            Err(why) => Err(why.into()),
+        }
+    }
+
+    async fn set(&self, key: &str, value: String) -> Result<(), CheckpointError> {
+        let _guard = self.write_lock.lock().await;
+
+        // Write to a temporary file first and rename it, so that a crash never leaves a half-written checkpoint. The
+        // file is synced before the rename and the directory after it, so that the new content survives a power loss.
+        let tmp_path = self.dir.join(format!("{key}.tmp"));
+        let mut tmp_file = fs::File::create(&tmp_path).await?;
+        tmp_file.write_all(value.as_bytes()).await?;
+        tmp_file.sync_all().await?;
+        fs::rename(&tmp_path, self.dir.join(key)).await?;
+        fs::File::open(&self.dir).await?.sync_all().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CheckpointError> {
+        let _guard = self.write_lock.lock().await;
+
+        match fs::remove_file(self.dir.join(key)).await {
+            Err(why) if why.kind() != ErrorKind::NotFound => Err(why.into()),
+            _ => Ok(()),
+        }
+    }
+}
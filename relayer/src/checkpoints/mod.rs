@@ -0,0 +1,112 @@
+use std::{io, sync::Arc};
+
+use async_trait::async_trait;
+use log::info;
+use redis::RedisError;
+use thiserror::Error;
+
+use crate::config::{CheckpointStoreKind, Config};
+
+mod file_store;
+mod redis_store;
+mod request_state;
+mod sled_store;
+
+pub use file_store::FileCheckpointStore;
+pub use redis_store::RedisCheckpointStore;
+pub use request_state::{
+    prune_finished_requests, read_request_state, write_request_state, FinishedRequests,
+    RequestState,
+};
+pub use sled_store::SledCheckpointStore;
+
+#[derive(Debug, Error)]
+#[error(transparent)]
+#[non_exhaustive]
+pub enum CheckpointError {
+    #[error("redis error")]
+    Redis(#[from] RedisError),
+
+    #[error("sled error")]
+    Sled(#[from] sled::Error),
+
+    #[error("io error")]
+    Io(#[from] io::Error),
+
+    #[error("stored checkpoint is corrupted")]
+    Corrupted(String),
+}
+
+impl CheckpointError {
+    /// A corrupted checkpoint needs an operator, a failing backend may recover on its own.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::Corrupted(_))
+    }
+}
+
+/// Persistent key-value storage for the relayer's progress: the last fully processed block of each
+/// listener and the state of every request it has voted on.
+///
+/// Implementations must tell a missing key (`Ok(None)`) apart from a failure to read it,
+/// so that a flaky backend never makes the relayer start over from the default block.
+#[async_trait]
+pub trait CheckpointStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, CheckpointError>;
+
+    async fn set(&self, key: &str, value: String) -> Result<(), CheckpointError>;
+
+    /// Deleting a missing key is not an error.
+    async fn delete(&self, key: &str) -> Result<(), CheckpointError>;
+}
+
+pub async fn open(config: &Config) -> Result<Arc<dyn CheckpointStore>, CheckpointError> {
+    let Config {
+        name,
+        checkpoint_store,
+        checkpoint_path,
+        redis_node,
+        ..
+    } = config;
+
+    Ok(match checkpoint_store {
+        CheckpointStoreKind::Redis => {
+            Arc::new(RedisCheckpointStore::connect(redis_node, name.clone()).await?)
+        }
+        CheckpointStoreKind::Sled => Arc::new(SledCheckpointStore::open(checkpoint_path, name)?),
+        CheckpointStoreKind::File => {
+            Arc::new(FileCheckpointStore::open(checkpoint_path, name.clone()).await?)
+        }
+    })
+}
+
+pub async fn read_first_unprocessed_block_number(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    key: &str,
+    default_block: u32,
+) -> Result<u32, CheckpointError> {
+    match checkpoint_store.get(key).await? {
+        Some(value) => {
+            let last_processed_block: u32 = value
+                .trim()
+                .parse()
+                .map_err(|_| CheckpointError::Corrupted(format!("{key}: {value:?}")))?;
+            Ok(last_processed_block + 1)
+        }
+        None => {
+            info!(
+                "No checkpoint stored under {key}, starting from the default block {default_block}"
+            );
+            Ok(default_block)
+        }
+    }
+}
+
+pub async fn write_last_processed_block(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    key: &str,
+    last_block_number: u32,
+) -> Result<(), CheckpointError> {
+    checkpoint_store
+        .set(key, last_block_number.to_string())
+        .await
+}
@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use log::warn;
+use redis::{aio::Connection as RedisConnection, Client as RedisClient, Cmd, FromRedisValue};
+use tokio::sync::Mutex;
+
+use super::{CheckpointError, CheckpointStore};
+
+pub struct RedisCheckpointStore {
+    name: String,
+    client: RedisClient,
+    // `None` after the connection has broken, until the next command reconnects.
+    connection: Mutex<Option<RedisConnection>>,
+}
+
+impl RedisCheckpointStore {
+    pub async fn connect(url: &str, name: String) -> Result<Self, CheckpointError> {
+        let client = RedisClient::open(url)?;
+        let connection = client.get_async_connection().await?;
+        Ok(Self {
+            name,
+            client,
+            connection: Mutex::new(Some(connection)),
+        })
+    }
+
+    // Runs the command, reconnecting first if the previous command has broken the connection.
+    async fn query<T: FromRedisValue>(&self, command: Cmd) -> Result<T, CheckpointError> {
+        let mut connection = self.connection.lock().await;
+        let mut live_connection = match connection.take() {
+            Some(live_connection) => live_connection,
+            None => self.client.get_async_connection().await?,
+        };
+
+        let result = command.query_async(&mut live_connection).await;
+        match &result {
+            Err(why) if why.is_connection_dropped() || why.is_io_error() => {
+                warn!("Redis connection is broken, reconnecting on the next command: {why}");
+            }
+            _ => *connection = Some(live_connection),
+        }
+        Ok(result?)
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for RedisCheckpointStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, CheckpointError> {
+        self.query(Cmd::get(format!("{}:{key}", self.name))).await
+    }
+
+    async fn set(&self, key: &str, value: String) -> Result<(), CheckpointError> {
+        self.query(Cmd::set(format!("{}:{key}", self.name), value))
+            .await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CheckpointError> {
+        self.query(Cmd::del(format!("{}:{key}", self.name))).await
+    }
+}
@@ -0,0 +1,164 @@
+use std::{collections::BTreeMap, mem, sync::Arc};
+
+use ethers::types::H256;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::{CheckpointError, CheckpointStore};
+
+/// Progress of this guardian's vote on a single cross-chain request.
+///
+/// Transitions: `Discovered` -> `Submitted` -> `Confirmed` -> `Finalized`, or `Failed` from any state.
+/// A vote whose transaction is dropped goes back to `Submitted` with a new one, or to `Skipped` if it is no longer
+/// needed. A vote that can never succeed ends up in `DeadLettered` and is not retried. `tx_hash` is the hash of the vote transaction (extrinsic) on the destination chain, and `resubmissions`
+/// counts the dropped transactions before it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequestState {
+    Discovered,
+    Submitted {
+        tx_hash: H256,
+        #[serde(default)]
+        resubmissions: u32,
+    },
+    Confirmed {
+        tx_hash: H256,
+        block_hash: H256,
+        block_number: u64,
+        #[serde(default)]
+        resubmissions: u32,
+    },
+    Finalized {
+        tx_hash: H256,
+    },
+    Failed {
+        reason: String,
+    },
+    Skipped {
+        reason: String,
+    },
+    DeadLettered {
+        reason: String,
+    },
+}
+
+impl RequestState {
+    /// Whether the vote is done with, so that the record is only needed while its block may be handled again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::Finalized { .. } | Self::Skipped { .. } | Self::DeadLettered { .. }
+        )
+    }
+}
+
+/// Requests from blocks whose events have all been handled, kept until the checkpoint moves past those blocks.
+#[derive(Debug, Default)]
+pub struct FinishedRequests {
+    by_block: BTreeMap<u32, Vec<[u8; 32]>>,
+}
+
+impl FinishedRequests {
+    pub fn add(&mut self, block_number: u32, request_hashes: Vec<[u8; 32]>) {
+        if !request_hashes.is_empty() {
+            self.by_block
+                .entry(block_number)
+                .or_default()
+                .extend(request_hashes);
+        }
+    }
+
+    /// Takes the requests from blocks up to `last_processed_block`.
+    pub fn take_processed(&mut self, last_processed_block: u32) -> Vec<[u8; 32]> {
+        let later = match last_processed_block.checked_add(1) {
+            Some(next_block) => self.by_block.split_off(&next_block),
+            None => BTreeMap::new(),
+        };
+        mem::replace(&mut self.by_block, later)
+            .into_values()
+            .flatten()
+            .collect()
+    }
+}
+
+fn request_key(request_hash: &[u8; 32]) -> String {
+    format!("request:0x{}", hex::encode(request_hash))
+}
+
+pub async fn read_request_state(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    request_hash: &[u8; 32],
+) -> Result<Option<RequestState>, CheckpointError> {
+    let key = request_key(request_hash);
+    match checkpoint_store.get(&key).await? {
+        Some(value) => serde_json::from_str(&value)
+            .map(Some)
+            .map_err(|why| CheckpointError::Corrupted(format!("{key}: {why}"))),
+        None => Ok(None),
+    }
+}
+
+pub async fn write_request_state(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    request_hash: &[u8; 32],
+    state: &RequestState,
+) -> Result<(), CheckpointError> {
+    let value = serde_json::to_string(state).expect("Request state is always serializable");
+    checkpoint_store
+        .set(&request_key(request_hash), value)
+        .await
+}
+
+/// Deletes the records of the requests that are done with from the blocks up to `last_processed_block`. They are behind
+/// the checkpoint, so they would only be read again after a reorg, when the on-chain checks before voting tell the
+/// same. Failing to delete them only leaves them in the store.
+pub async fn prune_finished_requests(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    finished_requests: &Mutex<FinishedRequests>,
+    last_processed_block: u32,
+) {
+    let request_hashes = finished_requests
+        .lock()
+        .await
+        .take_processed(last_processed_block);
+    if let Err(why) = prune_request_states(checkpoint_store, &request_hashes).await {
+        warn!("Failed to prune request states: {why:?}");
+    }
+}
+
+async fn prune_request_states(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    request_hashes: &[[u8; 32]],
+) -> Result<(), CheckpointError> {
+    for request_hash in request_hashes {
+        if read_request_state(checkpoint_store.clone(), request_hash)
+            .await?
+            .is_some_and(|state| state.is_terminal())
+        {
+            checkpoint_store.delete(&request_key(request_hash)).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_requests_up_to_last_processed_block() {
+        let mut finished_requests = FinishedRequests::default();
+        finished_requests.add(5, vec![[5; 32]]);
+        finished_requests.add(7, vec![[7; 32]]);
+        finished_requests.add(8, vec![]);
+        finished_requests.add(9, vec![[9; 32], [10; 32]]);
+
+        assert_eq!(finished_requests.take_processed(4), Vec::<[u8; 32]>::new());
+        assert_eq!(finished_requests.take_processed(7), vec![[5; 32], [7; 32]]);
+        assert_eq!(finished_requests.take_processed(7), Vec::<[u8; 32]>::new());
+        assert_eq!(
+            finished_requests.take_processed(u32::MAX),
+            vec![[9; 32], [10; 32]]
+        );
+    }
+}
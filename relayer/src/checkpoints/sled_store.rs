@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use sled::{Db, Tree};
+
+use super::{CheckpointError, CheckpointStore};
+
+pub struct SledCheckpointStore {
+    // Keep the database handle alive for as long as the tree is used.
+    _db: Db,
+    tree: Tree,
+}
+
+impl SledCheckpointStore {
+    pub fn open(path: impl AsRef<Path>, name: &str) -> Result<Self, CheckpointError> {
+        let db = sled::open(path)?;
+        let tree = db.open_tree(name)?;
+        Ok(Self { _db: db, tree })
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for SledCheckpointStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, CheckpointError> {
+        match self.tree.get(key)? {
+            Some(value) => String::from_utf8(value.to_vec())
+                .map(Some)
+                .map_err(|_| CheckpointError::Corrupted(format!("{key}: not valid UTF-8"))),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: String) -> Result<(), CheckpointError> {
+        self.tree.insert(key, value.as_bytes())?;
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CheckpointError> {
+        self.tree.remove(key)?;
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+}
@@ -1,3 +1,9 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::connections::eth::MIN_FEE_BUMP_PERCENT;
+
 #[derive(Debug, clap::Parser)]
 pub struct Config {
     #[arg(long)]
@@ -6,26 +12,37 @@ pub struct Config {
     #[arg(long)]
     pub dev: bool,
 
-    #[arg(long, default_value = "0")]
-    pub committee_id: u32,
-
     #[arg(long)]
     pub azero_contract_address: String,
 
-    #[arg(long, default_value = "../azero/artifacts/most.json")]
-    pub azero_contract_metadata: String,
+    #[arg(
+        long,
+        alias = "azero-node-wss-url",
+        value_delimiter = ',',
+        default_value = "ws://127.0.0.1:9944"
+    )]
+    pub azero_node_wss_urls: Vec<String>,
+
+    #[arg(long, default_value = "")]
+    pub azero_keystore_path: String,
+
+    #[arg(long, default_value = "")]
+    pub azero_keystore_password_file: String,
 
-    #[arg(long, default_value = "ws://127.0.0.1:9944")]
-    pub azero_node_wss_url: String,
+    #[arg(long, default_value = "")]
+    pub azero_seed_file: String,
 
     #[arg(long, default_value = "1000")]
     pub azero_max_event_handler_tasks: usize,
 
+    #[arg(long, default_value = "100")]
+    pub eth_max_event_handler_tasks: usize,
+
     #[arg(long)]
     pub eth_contract_address: String,
 
     #[arg(long, default_value = "")]
-    pub eth_keystore_password: String,
+    pub eth_keystore_password: SecretString,
 
     #[arg(long, default_value = "0")]
     pub dev_account_index: u32,
@@ -33,8 +50,22 @@ pub struct Config {
     #[arg(long, default_value = "")]
     pub eth_keystore_path: String,
 
-    #[arg(long, default_value = "http://127.0.0.1:8545")]
-    pub eth_node_http_url: String,
+    #[arg(
+        long,
+        alias = "eth-node-http-url",
+        value_delimiter = ',',
+        default_value = "http://127.0.0.1:8545"
+    )]
+    pub eth_node_http_urls: Vec<String>,
+
+    #[arg(long)]
+    pub eth_rpc_quorum: Option<u64>,
+
+    #[arg(long, default_value = "")]
+    pub eth_node_wss_url: String,
+
+    #[arg(long, default_value = "finalized")]
+    pub eth_finality: FinalityPolicy,
 
     #[arg(long, default_value = "10")]
     pub eth_tx_submission_retries: usize,
@@ -51,18 +82,168 @@ pub struct Config {
     #[arg(long, default_value = "1000")]
     pub sync_step: u32,
 
+    #[arg(long, default_value = "1")]
+    pub listener_restart_min_backoff_secs: u64,
+
+    #[arg(long, default_value = "300")]
+    pub listener_restart_max_backoff_secs: u64,
+
+    #[arg(long, default_value = "120")]
+    pub shutdown_timeout_secs: u64,
+
+    #[arg(long, value_enum, default_value = "redis")]
+    pub checkpoint_store: CheckpointStoreKind,
+
+    #[arg(long, default_value = "./checkpoints")]
+    pub checkpoint_path: String,
+
     #[arg(long, default_value = "redis://127.0.0.1:6379")]
     pub redis_node: String,
 
+    #[arg(long, default_value = "0.0.0.0:9615")]
+    pub http_address: String,
+
+    #[arg(long, default_value = "1800")]
+    pub liveness_window_secs: u64,
+
+    #[arg(long, default_value = "1200")]
+    pub readiness_max_block_lag_azero: u32,
+
+    #[arg(long, default_value = "100")]
+    pub readiness_max_block_lag_eth: u32,
+
     #[arg(long, default_value = "info")]
     pub rust_log: log::Level,
 
     #[arg(long, default_value = "200000")]
     pub eth_gas_limit: u32,
 
+    #[arg(long)]
+    pub eth_max_priority_fee_per_gas: Option<u64>,
+
+    #[arg(long, default_value = "300000000000")]
+    pub eth_max_fee_per_gas_cap: u64,
+
+    #[arg(long, default_value = "5")]
+    pub eth_fee_bump_blocks: u64,
+
+    #[arg(long, default_value = "20")]
+    pub eth_fee_bump_percent: u64,
+
+    #[arg(long, default_value = "50")]
+    pub eth_fee_cap_wait_blocks: u64,
+
     #[arg(long, default_value = "100000000000")]
     pub azero_ref_time_limit: u64,
 
     #[arg(long, default_value = "10000000")]
     pub azero_proof_size_limit: u64,
+
+    #[arg(long, default_value = "20")]
+    pub gas_safety_margin_percent: u64,
+}
+
+impl Config {
+    /// Checks combinations of arguments that clap cannot express, as most of them have empty defaults.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.azero_keystore_path.is_empty() && self.azero_keystore_password_file.is_empty() {
+            return Err(
+                "--azero-keystore-path requires --azero-keystore-password-file to be set"
+                    .to_string(),
+            );
+        }
+        if self.eth_node_http_urls.len() == 2 && self.eth_rpc_quorum.is_none() {
+            return Err(
+                "a majority of two --eth-node-http-urls is both of them, pass at least three urls or set --eth-rpc-quorum"
+                    .to_string(),
+            );
+        }
+        if self.eth_fee_bump_percent < MIN_FEE_BUMP_PERCENT {
+            return Err(format!(
+                "--eth-fee-bump-percent must be at least {MIN_FEE_BUMP_PERCENT}, as nodes reject replacements with lower fees"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Confirmations a vote tx waits for before it is recorded as confirmed. With a number of confirmations as the
+    /// Ethereum finality, waiting for more than that would only delay the finalized vote.
+    pub fn eth_tx_confirmations(&self) -> usize {
+        match self.eth_finality {
+            FinalityPolicy::Confirmations(confirmations) => {
+                self.eth_tx_min_confirmations.min(confirmations as usize)
+            }
+            FinalityPolicy::Finalized | FinalityPolicy::Safe => self.eth_tx_min_confirmations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CheckpointStoreKind {
+    /// Redis instance under `--redis-node`
+    Redis,
+    /// Embedded sled database under `--checkpoint-path`
+    Sled,
+    /// Plain text files under `--checkpoint-path`
+    File,
+}
+
+/// When an Ethereum block is considered final: `finalized`, `safe` or a number of confirmations.
+///
+/// Aleph Zero blocks are always followed up to the finalized head, as its finality is deterministic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinalityPolicy {
+    /// The `finalized` block tag
+    Finalized,
+    /// The `safe` block tag, for chains that do not support `finalized`
+    Safe,
+    /// Blocks with at least this many confirmations, counting the block itself
+    Confirmations(u32),
+}
+
+impl FromStr for FinalityPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "finalized" => Ok(Self::Finalized),
+            "safe" => Ok(Self::Safe),
+            _ => match s.parse::<u32>() {
+                Ok(confirmations) if confirmations > 0 => Ok(Self::Confirmations(confirmations)),
+                _ => Err(format!(
+                    "expected `finalized`, `safe` or a positive number of confirmations, got `{s}`"
+                )),
+            },
+        }
+    }
+}
+
+/// A secret passed on the command line.
+///
+/// It is zeroized on drop and redacted from `Debug` output, so it never ends up in the logs.
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "\"\"")
+        } else {
+            write!(f, "\"<redacted>\"")
+        }
+    }
+}
+
+impl FromStr for SecretString {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_owned()))
+    }
 }
@@ -1,8 +1,20 @@
-use aleph_client::{Connection, KeyPair, SignedConnection};
+use std::sync::Arc;
+
+use aleph_client::{utility::BlocksApi, Connection, KeyPair, SignedConnection};
+use log::{info, warn};
+use tokio::{
+    sync::Mutex,
+    time::{sleep, timeout, Duration},
+};
 
 pub type AzeroWsConnection = Connection;
 pub type SignedAzeroWsConnection = SignedConnection;
 
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+// Wait time after none of the endpoints could be connected to.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
 pub async fn init(url: &str) -> AzeroWsConnection {
     Connection::new(url).await
 }
@@ -11,3 +23,89 @@ pub fn sign(connection: &AzeroWsConnection, keypair: &KeyPair) -> SignedAzeroWsC
     let signer = KeyPair::new(keypair.signer().clone());
     SignedAzeroWsConnection::from_connection(connection.clone(), signer)
 }
+
+/// Signed connections to a list of Aleph Zero nodes, one of which is used at a time.
+///
+/// A websocket that has been dropped is never reopened by the client, so consumers should get a fresh connection
+/// from here whenever they (re)start. If the current node no longer responds, the pool reconnects, moving on
+/// to the next endpoint on failure.
+pub struct AzeroConnectionPool {
+    urls: Vec<String>,
+    keypair: KeyPair,
+    current: Mutex<Option<(usize, Arc<SignedAzeroWsConnection>)>>,
+}
+
+impl AzeroConnectionPool {
+    pub fn new(urls: Vec<String>, keypair: KeyPair) -> Self {
+        assert!(
+            !urls.is_empty(),
+            "At least one Aleph Zero node url must be provided"
+        );
+
+        Self {
+            urls,
+            keypair,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Returns a connection that has just responded, waiting until one of the nodes is reachable.
+    pub async fn connection(&self) -> Arc<SignedAzeroWsConnection> {
+        let mut current = self.current.lock().await;
+
+        let mut index = match &*current {
+            Some((index, connection)) => {
+                if is_alive(connection).await {
+                    return connection.clone();
+                }
+                warn!(
+                    "Aleph Zero node {} does not respond, reconnecting",
+                    self.urls[*index]
+                );
+                *index
+            }
+            None => 0,
+        };
+
+        let mut failures = 0;
+        loop {
+            let url = &self.urls[index];
+            match try_connect(url).await {
+                Some(connection) => {
+                    info!("Connected to Aleph Zero node {url}");
+                    let connection = Arc::new(sign(&connection, &self.keypair));
+                    *current = Some((index, connection.clone()));
+                    return connection;
+                }
+                None => warn!("Cannot connect to Aleph Zero node {url}"),
+            }
+
+            index = (index + 1) % self.urls.len();
+            failures += 1;
+            if failures % self.urls.len() == 0 {
+                sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn is_alive(connection: &SignedAzeroWsConnection) -> bool {
+    matches!(
+        timeout(PING_TIMEOUT, connection.get_finalized_block_hash()).await,
+        Ok(Ok(_))
+    )
+}
+
+async fn try_connect(url: &str) -> Option<AzeroWsConnection> {
+    let url = url.to_string();
+    // `Connection::new` panics once it runs out of retries, so it runs in its own task.
+    let mut task = tokio::spawn(async move { init(&url).await });
+    match timeout(CONNECT_TIMEOUT, &mut task).await {
+        Ok(Ok(connection)) => Some(connection),
+        Ok(Err(_)) => None,
+        Err(_) => {
+            task.abort();
+            None
+        }
+    }
+}
@@ -1,13 +1,45 @@
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use async_trait::async_trait;
 use ethers::{
+    contract::ContractError,
     prelude::SignerMiddleware,
-    providers::{Http, Provider, ProviderError, ProviderExt},
-    signers::{LocalWallet, WalletError},
+    providers::{
+        is_local_endpoint, Http, HttpClientError, JsonRpcClient, JsonRpcError, Middleware,
+        MiddlewareError, Provider, ProviderError, Quorum, QuorumError, QuorumProvider, RpcError,
+        WeightedProvider, DEFAULT_LOCAL_POLL_INTERVAL,
+    },
+    signers::{LocalWallet, Signer, WalletError},
+    types::{BlockNumber, Chain, U256},
 };
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Serialize};
 use thiserror::Error;
+use tokio::sync::Mutex;
 
-pub type EthConnection = Provider<Http>;
+pub type EthConnection = Provider<EthRpc>;
 pub type SignedEthConnection = SignerMiddleware<EthConnection, LocalWallet>;
 
+// Methods whose answer depends on the mempool of the node, or that must reach a single node only.
+const SINGLE_NODE_METHODS: [&str; 9] = [
+    "eth_sendRawTransaction",
+    "eth_sendTransaction",
+    "eth_estimateGas",
+    "eth_getTransactionCount",
+    "eth_getTransactionByHash",
+    "eth_gasPrice",
+    "eth_maxPriorityFeePerGas",
+    "eth_feeHistory",
+    "eth_chainId",
+];
+
 #[derive(Debug, Error)]
 #[error(transparent)]
 #[non_exhaustive]
@@ -17,10 +49,157 @@ pub enum EthConnectionError {
 
     #[error("wallet error")]
     Wallet(#[from] WalletError),
+
+    #[error("invalid node url")]
+    InvalidUrl(String),
+
+    #[error("no node urls provided")]
+    NoNodes,
+
+    #[error("a majority of two nodes needs both of them")]
+    NoFailover,
+}
+
+/// Error of a request sent either to all nodes or to a single one.
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub enum EthRpcError {
+    Quorum(#[from] QuorumError),
+    Node(#[from] HttpClientError),
+}
+
+impl RpcError for EthRpcError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            Self::Quorum(why) => why.as_error_response(),
+            Self::Node(why) => why.as_error_response(),
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            Self::Quorum(why) => why.as_serde_error(),
+            Self::Node(why) => why.as_serde_error(),
+        }
+    }
+}
+
+impl From<EthRpcError> for ProviderError {
+    fn from(why: EthRpcError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(why))
+    }
+}
+
+/// JSON-RPC client over a list of Ethereum nodes.
+///
+/// Chain data (blocks, logs, calls) is requested from all nodes and accepted once a quorum of them agrees on it.
+/// Transactions, gas estimates, nonces and fees depend on the mempool of each node, so they go to a single node
+/// instead, and to the next one whenever it cannot be reached. Errors returned by a reachable node (e.g. a revert or
+/// a used nonce) are passed on without failing over.
+#[derive(Debug)]
+pub struct EthRpc {
+    quorum: QuorumProvider<Http>,
+    nodes: Vec<Http>,
+    current_node: AtomicUsize,
+}
+
+impl EthRpc {
+    async fn request_single_node<T, R>(&self, method: &str, params: T) -> Result<R, EthRpcError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let first_node = self.current_node.load(Ordering::Relaxed);
+        let mut result = None;
+        for attempt in 0..self.nodes.len() {
+            let index = (first_node + attempt) % self.nodes.len();
+            match self.nodes[index].request(method, &params).await {
+                Err(why) if why.as_error_response().is_none() => {
+                    warn!("Ethereum node {index} cannot handle {method}, failing over: {why}");
+                    self.current_node
+                        .store((index + 1) % self.nodes.len(), Ordering::Relaxed);
+                    result = Some(Err(why.into()));
+                }
+                other => return other.map_err(Into::into),
+            }
+        }
+        result.expect("There is at least one node")
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for EthRpc {
+    type Error = EthRpcError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if self.nodes.len() > 1 && SINGLE_NODE_METHODS.contains(&method) {
+            self.request_single_node(method, params).await
+        } else {
+            Ok(self.quorum.request(method, params).await?)
+        }
+    }
 }
 
-pub async fn connect(url: &str) -> EthConnection {
-    Provider::<Http>::connect(url).await
+/// Connects to all given Ethereum nodes at once.
+///
+/// Chain data is accepted once `quorum` of the nodes (or a majority, if not set) returned the same response, so a node
+/// that is down or lagging behind is tolerated, but a single node cannot make us act on a block or log the others have
+/// not seen. With the default majority at least three nodes are needed for that, as a majority of two nodes is both of
+/// them. Transactions are sent through one node at a time, see [`EthRpc`].
+pub async fn connect(
+    urls: &[String],
+    quorum: Option<u64>,
+) -> Result<EthConnection, EthConnectionError> {
+    if urls.is_empty() {
+        return Err(EthConnectionError::NoNodes);
+    }
+    if urls.len() == 2 && quorum.is_none() {
+        return Err(EthConnectionError::NoFailover);
+    }
+
+    let nodes = urls
+        .iter()
+        .map(|url| {
+            Http::from_str(url)
+                .map_err(|why| EthConnectionError::InvalidUrl(format!("{url}: {why}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let quorum = match quorum {
+        Some(weight) => Quorum::Weight(weight),
+        None => Quorum::Majority,
+    };
+
+    info!(
+        "Connecting to {} Ethereum nodes with quorum {quorum:?}",
+        urls.len()
+    );
+    let mut connection = Provider::new(EthRpc {
+        quorum: QuorumProvider::new(
+            quorum,
+            nodes.iter().cloned().map(WeightedProvider::new).collect(),
+        ),
+        nodes,
+        current_node: AtomicUsize::new(0),
+    });
+
+    // Poll at the same rate as `ProviderExt::connect` would for a single node.
+    if urls.iter().all(|url| is_local_endpoint(url)) {
+        connection.set_interval(DEFAULT_LOCAL_POLL_INTERVAL);
+    } else {
+        let chain_id = connection.get_chainid().await?;
+        if let Some(block_time) = Chain::try_from(chain_id)
+            .ok()
+            .and_then(|chain| chain.average_blocktime_hint())
+        {
+            connection.set_interval(block_time / 2);
+        }
+    }
+
+    Ok(connection)
 }
 
 pub async fn sign(
@@ -33,3 +212,227 @@ pub async fn sign(
             .unwrap(),
     )
 }
+
+/// Hands out nonces for the guardian's Ethereum transactions, so that concurrent votes neither collide nor wait
+/// for each other.
+///
+/// Nonces are counted locally, starting from the node's pending transaction count. Whenever a nonce may have been
+/// left unused or turns out to be taken, [`NonceManager::resync`] makes the next one be read from the node again,
+/// which fills any gap that would otherwise block all later transactions. The count is only read while no reserved
+/// nonce is waiting to be broadcast, as it would not include those yet.
+pub struct NonceManager {
+    eth_connection: Arc<SignedEthConnection>,
+    next_nonce: Mutex<Option<U256>>,
+    reserved: AtomicUsize,
+    resync_requested: AtomicBool,
+}
+
+/// A nonce handed out by [`NonceManager::next`], to be dropped once a transaction with it has been broadcast or
+/// cannot be.
+pub struct NonceReservation<'a> {
+    nonce: U256,
+    reserved: &'a AtomicUsize,
+}
+
+impl NonceReservation<'_> {
+    pub fn nonce(&self) -> U256 {
+        self.nonce
+    }
+}
+
+impl Drop for NonceReservation<'_> {
+    fn drop(&mut self) {
+        self.reserved.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl NonceManager {
+    pub fn new(eth_connection: Arc<SignedEthConnection>) -> Self {
+        Self {
+            eth_connection,
+            next_nonce: Mutex::new(None),
+            reserved: AtomicUsize::new(0),
+            resync_requested: AtomicBool::new(false),
+        }
+    }
+
+    /// Reserves the next nonce.
+    pub async fn next(&self) -> Result<NonceReservation<'_>, ProviderError> {
+        let mut next_nonce = self.next_nonce.lock().await;
+        // New reservations are only made under the lock, so none can appear until the count is read.
+        if self.reserved.load(Ordering::Acquire) == 0
+            && self.resync_requested.swap(false, Ordering::AcqRel)
+        {
+            *next_nonce = None;
+        }
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => {
+                self.eth_connection
+                    .inner()
+                    .get_transaction_count(
+                        self.eth_connection.signer().address(),
+                        Some(BlockNumber::Pending.into()),
+                    )
+                    .await?
+            }
+        };
+        *next_nonce = Some(nonce + 1);
+        self.reserved.fetch_add(1, Ordering::AcqRel);
+        Ok(NonceReservation {
+            nonce,
+            reserved: &self.reserved,
+        })
+    }
+
+    /// Makes the next nonce be read from the node, as soon as all nonces reserved until then are dropped.
+    pub fn resync(&self) {
+        self.resync_requested.store(true, Ordering::Release);
+    }
+}
+
+/// EIP-1559 fees of a transaction, in wei.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fees {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Nodes only accept a transaction replacing another one with the same nonce if it raises both fees by this much.
+pub const MIN_FEE_BUMP_PERCENT: u64 = 10;
+
+/// How the fees of the guardian's transactions are chosen and raised while they are stuck.
+///
+/// The max fee never exceeds `max_fee_per_gas_cap`, even if a transaction has to wait for base fees to drop then.
+#[derive(Debug, Clone, Copy)]
+pub struct FeeStrategy {
+    /// Taken from the node's estimate if not set
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub max_fee_per_gas_cap: U256,
+    pub bump_percent: u64,
+}
+
+impl FeeStrategy {
+    /// Fees for a new transaction, leaving room for the base fee to double before it is mined.
+    pub async fn fees(&self, eth_connection: &SignedEthConnection) -> Result<Fees, ProviderError> {
+        let (estimated_max_fee, estimated_priority_fee) =
+            eth_connection.inner().estimate_eip1559_fees(None).await?;
+        let max_priority_fee_per_gas = self
+            .max_priority_fee_per_gas
+            .unwrap_or(estimated_priority_fee);
+        let max_fee_per_gas =
+            estimated_max_fee.saturating_sub(estimated_priority_fee) + max_priority_fee_per_gas;
+        Ok(self.capped(max_fee_per_gas, max_priority_fee_per_gas))
+    }
+
+    /// Fees for a replacement of a transaction sent with `fees`. Returns `None` if the cap does not leave room for
+    /// a replacement the nodes accept, i.e. one with both fees raised by [`MIN_FEE_BUMP_PERCENT`].
+    pub fn bump(&self, fees: Fees) -> Option<Fees> {
+        let bump = |fee: U256, percent: u64| fee + fee * percent / 100;
+        let bumped_fees = self.capped(
+            bump(fees.max_fee_per_gas, self.bump_percent),
+            bump(fees.max_priority_fee_per_gas, self.bump_percent),
+        );
+        let accepted = bumped_fees.max_fee_per_gas
+            >= bump(fees.max_fee_per_gas, MIN_FEE_BUMP_PERCENT)
+            && bumped_fees.max_priority_fee_per_gas
+                >= bump(fees.max_priority_fee_per_gas, MIN_FEE_BUMP_PERCENT);
+        (accepted && bumped_fees != fees).then_some(bumped_fees)
+    }
+
+    fn capped(&self, max_fee_per_gas: U256, max_priority_fee_per_gas: U256) -> Fees {
+        let max_fee_per_gas = max_fee_per_gas.min(self.max_fee_per_gas_cap);
+        Fees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: max_priority_fee_per_gas.min(max_fee_per_gas),
+        }
+    }
+}
+
+/// Whether a transaction has been rejected by the node because its nonce is already used by another one.
+pub fn is_nonce_error<M: Middleware>(why: &ContractError<M>) -> bool {
+    node_error_contains(
+        why,
+        &["nonce too low", "replacement transaction underpriced"],
+    )
+}
+
+/// Whether a replacement transaction has been rejected by the node because its fees are not raised enough.
+pub fn is_underpriced_error<M: Middleware>(why: &ContractError<M>) -> bool {
+    node_error_contains(why, &["underpriced"])
+}
+
+// Whether the error response returned by the node contains any of the patterns.
+fn node_error_contains<M: Middleware>(why: &ContractError<M>, patterns: &[&str]) -> bool {
+    let response = match why {
+        ContractError::MiddlewareError { e } => e.as_error_response(),
+        ContractError::ProviderError { e } => e.as_error_response(),
+        _ => None,
+    };
+    response.is_some_and(|response| {
+        let message = response.message.to_lowercase();
+        patterns.iter().any(|pattern| message.contains(pattern))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_error(code: i64, message: &str) -> ContractError<EthConnection> {
+        let error = JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        };
+        ContractError::ProviderError {
+            e: EthRpcError::Node(HttpClientError::JsonRpcError(error)).into(),
+        }
+    }
+
+    #[test]
+    fn recognizes_nonce_errors_returned_by_node() {
+        assert!(is_nonce_error(&node_error(-32000, "nonce too low")));
+        assert!(is_nonce_error(&node_error(
+            -32000,
+            "replacement transaction underpriced"
+        )));
+        assert!(!is_nonce_error(&node_error(-32000, "insufficient funds")));
+    }
+
+    #[test]
+    fn ignores_nonce_errors_not_returned_by_node() {
+        let why = ContractError::<EthConnection>::ProviderError {
+            e: ProviderError::CustomError("nonce too low".to_string()),
+        };
+        assert!(!is_nonce_error(&why));
+    }
+
+    fn fee_strategy(bump_percent: u64) -> FeeStrategy {
+        FeeStrategy {
+            max_priority_fee_per_gas: None,
+            max_fee_per_gas_cap: 1000.into(),
+            bump_percent,
+        }
+    }
+
+    fn fees(max_fee_per_gas: u64, max_priority_fee_per_gas: u64) -> Fees {
+        Fees {
+            max_fee_per_gas: max_fee_per_gas.into(),
+            max_priority_fee_per_gas: max_priority_fee_per_gas.into(),
+        }
+    }
+
+    #[test]
+    fn bumps_fees_up_to_cap() {
+        assert_eq!(fee_strategy(20).bump(fees(500, 100)), Some(fees(600, 120)));
+        assert_eq!(fee_strategy(20).bump(fees(900, 100)), Some(fees(1000, 120)));
+    }
+
+    #[test]
+    fn does_not_bump_fees_below_replacement_minimum() {
+        assert_eq!(fee_strategy(20).bump(fees(950, 100)), None);
+        assert_eq!(fee_strategy(20).bump(fees(1000, 100)), None);
+        assert_eq!(fee_strategy(5).bump(fees(500, 100)), None);
+    }
+}
@@ -1,7 +1,5 @@
 pub mod azero;
 pub mod eth;
-pub mod redis_helpers;
 
-pub use azero::AzeroWsConnection;
+pub use azero::{AzeroConnectionPool, AzeroWsConnection};
 pub use eth::{EthConnection, EthConnectionError};
-pub use redis_helpers::{read_first_unprocessed_block_number, write_last_processed_block};
@@ -1,193 +0,0 @@
-use std::{
-    collections::HashMap,
-    str,
-    str::{FromStr, Utf8Error},
-};
-
-use aleph_client::{
-    contract::{
-        event::{translate_events, BlockDetails, ContractEvent},
-        ContractInstance,
-    },
-    contract_transcode::{ContractMessageTranscoder, Value, Value::Seq},
-    pallets::contract::ContractsUserApi,
-    sp_weights::weight_v2::Weight,
-    AccountId, AlephConfig, SignedConnection, TxInfo, TxStatus,
-};
-use log::trace;
-use subxt::events::Events;
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-#[error(transparent)]
-#[non_exhaustive]
-pub enum AzeroContractError {
-    #[error("aleph-client error")]
-    AlephClient(#[from] anyhow::Error),
-
-    #[error("not account id")]
-    NotAccountId(String),
-
-    #[error("Invalid UTF-8 sequence")]
-    InvalidUTF8(#[from] Utf8Error),
-
-    #[error("Missing or invalid field")]
-    MissingOrInvalidField(String),
-}
-
-pub struct MostInstance {
-    pub contract: ContractInstance,
-    pub address: AccountId,
-    pub transcoder: ContractMessageTranscoder,
-    pub ref_time_limit: u64,
-    pub proof_size_limit: u64,
-}
-
-impl MostInstance {
-    pub fn new(
-        address: &str,
-        metadata_path: &str,
-        ref_time_limit: u64,
-        proof_size_limit: u64,
-    ) -> Result<Self, AzeroContractError> {
-        let address = AccountId::from_str(address)
-            .map_err(|why| AzeroContractError::NotAccountId(why.to_string()))?;
-        Ok(Self {
-            address: address.clone(),
-            transcoder: ContractMessageTranscoder::load(metadata_path)?,
-            contract: ContractInstance::new(address, metadata_path)?,
-            ref_time_limit,
-            proof_size_limit,
-        })
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    pub async fn receive_request(
-        &self,
-        signed_connection: &SignedConnection,
-        request_hash: [u8; 32],
-        committee_id: u128,
-        dest_token_address: [u8; 32],
-        amount: u128,
-        dest_receiver_address: [u8; 32],
-        request_nonce: u128,
-    ) -> Result<TxInfo, AzeroContractError> {
-        let args = [
-            committee_id.to_string(),
-            bytes32_to_str(&request_hash),
-            bytes32_to_str(&dest_token_address),
-            amount.to_string(),
-            bytes32_to_str(&dest_receiver_address),
-            request_nonce.to_string(),
-        ];
-
-        let data = self.transcoder.encode("receive_request", args)?;
-        signed_connection
-            .call(
-                self.address.clone(),
-                0,
-                Weight {
-                    ref_time: self.ref_time_limit,
-                    proof_size: self.proof_size_limit,
-                },
-                None,
-                data,
-                TxStatus::Finalized,
-            )
-            .await
-            .map_err(AzeroContractError::AlephClient)
-    }
-
-    pub fn filter_events(
-        &self,
-        events: Events<AlephConfig>,
-        block_details: BlockDetails,
-    ) -> Vec<ContractEvent> {
-        translate_events(events.iter(), &[&self.contract], Some(block_details))
-            .into_iter()
-            .filter_map(|event_res| {
-                if let Ok(event) = event_res {
-                    Some(event)
-                } else {
-                    trace!("Failed to translate event: {:?}", event_res);
-                    None
-                }
-            })
-            .collect()
-    }
-}
-
-pub struct CrosschainTransferRequestData {
-    pub dest_token_address: [u8; 32],
-    pub amount: u128,
-    pub dest_receiver_address: [u8; 32],
-    pub request_nonce: u128,
-}
-
-pub fn get_request_event_data(
-    data: &HashMap<String, Value>,
-) -> Result<CrosschainTransferRequestData, AzeroContractError> {
-    let dest_token_address: [u8; 32] = decode_seq_field(data, "dest_token_address")?;
-    let amount: u128 = decode_uint_field(data, "amount")?;
-    let dest_receiver_address: [u8; 32] = decode_seq_field(data, "dest_receiver_address")?;
-    let request_nonce: u128 = decode_uint_field(data, "request_nonce")?;
-
-    Ok(CrosschainTransferRequestData {
-        dest_token_address,
-        amount,
-        dest_receiver_address,
-        request_nonce,
-    })
-}
-
-fn decode_seq_field(
-    data: &HashMap<String, Value>,
-    field: &str,
-) -> Result<[u8; 32], AzeroContractError> {
-    if let Some(Seq(seq_data)) = data.get(field) {
-        match seq_data
-            .elems()
-            .iter()
-            .try_fold(Vec::new(), |mut v, x| match x {
-                Value::UInt(x) => {
-                    v.push(*x as u8);
-                    Ok(v)
-                }
-                _ => Err(AzeroContractError::MissingOrInvalidField(format!(
-                    "Seq under data field {:?} contains elements of incorrect type",
-                    field
-                ))),
-            })?
-            .try_into()
-        {
-            Ok(x) => Ok(x),
-            Err(_) => Err(AzeroContractError::MissingOrInvalidField(format!(
-                "Seq under data field {:?} has incorrect length",
-                field
-            ))),
-        }
-    } else {
-        Err(AzeroContractError::MissingOrInvalidField(format!(
-            "Data field {:?} couldn't be found or has incorrect format",
-            field
-        )))
-    }
-}
-
-fn decode_uint_field(
-    data: &HashMap<String, Value>,
-    field: &str,
-) -> Result<u128, AzeroContractError> {
-    if let Some(Value::UInt(x)) = data.get(field) {
-        Ok(*x)
-    } else {
-        Err(AzeroContractError::MissingOrInvalidField(format!(
-            "Data field {:?} couldn't be found or has incorrect format",
-            field
-        )))
-    }
-}
-
-fn bytes32_to_str(data: &[u8; 32]) -> String {
-    "0x".to_owned() + &hex::encode(data)
-}
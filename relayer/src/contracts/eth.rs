@@ -1,3 +0,0 @@
-use ethers::contract::abigen;
-
-abigen!(Most, "../eth/artifacts/contracts/Most.sol/Most.json");
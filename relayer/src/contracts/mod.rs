@@ -1,5 +0,0 @@
-mod azero;
-mod eth;
-
-pub use azero::*;
-pub use eth::*;
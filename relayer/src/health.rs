@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use aleph_client::SignedConnectionApi;
+use ethers::{core::types::Address, providers::Middleware};
+use most_client::{
+    azero::{MostInstance, WeightLimits},
+    eth::Most,
+};
+use once_cell::sync::Lazy;
+use tokio::time::{timeout, Duration, Instant};
+
+use crate::{
+    checkpoints::CheckpointStore,
+    config::Config,
+    connections::{eth::SignedEthConnection, AzeroConnectionPool},
+    metrics::{self, Chain},
+};
+
+const CHECKPOINT_STORE_PROBE_KEY: &str = "health_probe";
+const AZERO_CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Last time each listener loop moved forward.
+static LAST_ADVANCED: Lazy<Mutex<HashMap<Chain, Instant>>> = Lazy::new(Default::default);
+// Listeners that have not started yet are measured from here.
+static STARTED_AT: Lazy<Instant> = Lazy::new(Instant::now);
+
+pub fn start_clock() {
+    Lazy::force(&STARTED_AT);
+}
+
+/// Called by the listener loops whenever they make progress, see [`liveness`].
+pub fn listener_advanced(chain: Chain) {
+    LAST_ADVANCED
+        .lock()
+        .expect("Lock is not poisoned")
+        .insert(chain, Instant::now());
+}
+
+/// Fails if a listener loop has not moved forward within `liveness_window_secs`.
+pub fn liveness(config: &Config) -> Result<(), Vec<String>> {
+    let window = Duration::from_secs(config.liveness_window_secs);
+    let last_advanced = LAST_ADVANCED.lock().expect("Lock is not poisoned");
+
+    let problems: Vec<String> = [Chain::AlephZero, Chain::Ethereum]
+        .into_iter()
+        .filter_map(|chain| {
+            let since = last_advanced.get(&chain).unwrap_or(&STARTED_AT).elapsed();
+            (since > window).then(|| format!("{chain:?} listener has not advanced for {since:?}"))
+        })
+        .collect();
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+/// Everything needed to tell whether the guardian can take part in voting.
+pub struct ReadinessCheck {
+    pub config: Arc<Config>,
+    pub azero_connections: Arc<AzeroConnectionPool>,
+    pub eth_connection: Arc<SignedEthConnection>,
+    pub checkpoint_store: Arc<dyn CheckpointStore>,
+}
+
+impl ReadinessCheck {
+    /// Succeeds if the checkpoint store and both RPC nodes respond, the guardian is a member of the current committee
+    /// on both chains and neither listener is too far behind finality. Otherwise returns all failed checks.
+    pub async fn run(&self) -> Result<(), Vec<String>> {
+        let (checkpoint_store, azero, eth) = tokio::join!(
+            self.check_checkpoint_store(),
+            self.check_azero(),
+            self.check_eth()
+        );
+
+        let mut problems: Vec<String> = [checkpoint_store, azero, eth]
+            .into_iter()
+            .filter_map(Result::err)
+            .collect();
+
+        let Config {
+            readiness_max_block_lag_azero,
+            readiness_max_block_lag_eth,
+            ..
+        } = &*self.config;
+        for (chain, max_lag) in [
+            (Chain::AlephZero, *readiness_max_block_lag_azero),
+            (Chain::Ethereum, *readiness_max_block_lag_eth),
+        ] {
+            let lag = metrics::block_lag(chain);
+            if lag > max_lag.into() {
+                problems.push(format!(
+                    "{chain:?} listener is {lag} blocks behind finality"
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    async fn check_checkpoint_store(&self) -> Result<(), String> {
+        self.checkpoint_store
+            .get(CHECKPOINT_STORE_PROBE_KEY)
+            .await
+            .map(|_| ())
+            .map_err(|why| format!("checkpoint store is not reachable: {why:?}"))
+    }
+
+    async fn check_azero(&self) -> Result<(), String> {
+        let Config {
+            azero_contract_address,
+            azero_ref_time_limit,
+            azero_proof_size_limit,
+            gas_safety_margin_percent,
+            ..
+        } = &*self.config;
+
+        // Only returns a connection that has just responded, but waits for a node to come back otherwise.
+        let azero_connection = timeout(
+            AZERO_CONNECTION_TIMEOUT,
+            self.azero_connections.connection(),
+        )
+        .await
+        .map_err(|_| "no AlephZero node responds".to_string())?;
+
+        let contract = MostInstance::new(
+            azero_contract_address,
+            WeightLimits {
+                ref_time_limit: *azero_ref_time_limit,
+                proof_size_limit: *azero_proof_size_limit,
+                gas_safety_margin_percent: *gas_safety_margin_percent,
+            },
+        )
+        .map_err(|why| format!("{why:?}"))?;
+        let committee_id = contract
+            .get_current_committee_id(&*azero_connection)
+            .await
+            .map_err(|why| format!("cannot read the AlephZero committee: {why:?}"))?;
+        let guardian = azero_connection.account_id();
+        let is_member = contract
+            .is_in_committee(&*azero_connection, committee_id, guardian)
+            .await
+            .map_err(|why| format!("cannot read the AlephZero committee: {why:?}"))?;
+
+        if !is_member {
+            return Err(format!(
+                "{guardian} is not a member of the current AlephZero committee {committee_id}"
+            ));
+        }
+        Ok(())
+    }
+
+    async fn check_eth(&self) -> Result<(), String> {
+        self.eth_connection
+            .get_block_number()
+            .await
+            .map_err(|why| format!("Ethereum node does not respond: {why:?}"))?;
+
+        let address = self
+            .config
+            .eth_contract_address
+            .parse::<Address>()
+            .map_err(|why| format!("{why:?}"))?;
+        let contract = Most::new(address, self.eth_connection.clone());
+        let committee_id = contract
+            .committee_id()
+            .call()
+            .await
+            .map_err(|why| format!("cannot read the Ethereum committee: {why:?}"))?;
+        let guardian = self.eth_connection.address();
+        let is_member = contract
+            .is_in_committee(committee_id, guardian)
+            .call()
+            .await
+            .map_err(|why| format!("cannot read the Ethereum committee: {why:?}"))?;
+
+        if !is_member {
+            return Err(format!(
+                "{guardian:?} is not a member of the current Ethereum committee {committee_id}"
+            ));
+        }
+        Ok(())
+    }
+}
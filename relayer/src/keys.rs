@@ -0,0 +1,261 @@
+use std::{env, fs, io};
+
+use aleph_client::{KeyPair, Pair, RawKeyPair};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use scrypt::{scrypt, Params as ScryptParams};
+use serde::Deserialize;
+use thiserror::Error;
+use xsalsa20poly1305::{aead::Aead, KeyInit, XSalsa20Poly1305};
+use zeroize::Zeroizing;
+
+use crate::config::Config;
+
+pub const AZERO_SEED_ENV_VAR: &str = "AZERO_SEED";
+
+// Layout of the polkadot-js keystore: https://github.com/polkadot-js/common/tree/master/packages/keyring
+const SCRYPT_SALT_LENGTH: usize = 32;
+const SCRYPT_PARAMS_LENGTH: usize = 12;
+const NONCE_LENGTH: usize = 24;
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+const SECRET_KEY_LENGTH: usize = 64;
+const PUBLIC_KEY_LENGTH: usize = 32;
+
+#[derive(Debug, Error)]
+#[error(transparent)]
+#[non_exhaustive]
+pub enum AzeroKeyError {
+    #[error("io error")]
+    Io(#[from] io::Error),
+
+    #[error("keystore is not valid json")]
+    Json(#[from] serde_json::Error),
+
+    #[error("keystore content is not valid base64")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("no signing key source was provided")]
+    NoKeySource,
+
+    #[error("more than one signing key source was provided")]
+    AmbiguousKeySource,
+
+    #[error("unsupported keystore format")]
+    UnsupportedKeystore(String),
+
+    #[error("cannot decrypt keystore, is the password correct?")]
+    Decryption,
+
+    #[error("invalid secret key")]
+    InvalidSecret,
+}
+
+#[derive(Deserialize)]
+struct PolkadotJsKeystore {
+    encoded: String,
+    encoding: PolkadotJsEncoding,
+}
+
+#[derive(Deserialize)]
+struct PolkadotJsEncoding {
+    content: Vec<String>,
+    #[serde(rename = "type")]
+    kind: Vec<String>,
+}
+
+/// Loads the guardian's sr25519 key used to sign Aleph Zero transactions.
+///
+/// Exactly one source has to be configured: a polkadot-js JSON keystore with a password file,
+/// a file containing a seed/mnemonic/secret URI or the `AZERO_SEED` environment variable.
+/// The environment variable is cleared once it has been read.
+pub fn azero_keypair(config: &Config) -> Result<KeyPair, AzeroKeyError> {
+    let Config {
+        azero_keystore_path,
+        azero_keystore_password_file,
+        azero_seed_file,
+        ..
+    } = config;
+
+    let seed_from_env = env::var(AZERO_SEED_ENV_VAR).ok().map(Zeroizing::new);
+    env::remove_var(AZERO_SEED_ENV_VAR);
+
+    let sources = [
+        !azero_keystore_path.is_empty(),
+        !azero_seed_file.is_empty(),
+        seed_from_env.is_some(),
+    ];
+    match sources.iter().filter(|source| **source).count() {
+        0 => return Err(AzeroKeyError::NoKeySource),
+        1 => {}
+        _ => return Err(AzeroKeyError::AmbiguousKeySource),
+    }
+
+    let pair = if !azero_keystore_path.is_empty() {
+        let keystore = Zeroizing::new(fs::read_to_string(azero_keystore_path)?);
+        let password = Zeroizing::new(fs::read_to_string(azero_keystore_password_file)?);
+        decrypt_polkadot_js_keystore(&keystore, password.trim_end_matches(['\n', '\r']))?
+    } else if !azero_seed_file.is_empty() {
+        let seed = Zeroizing::new(fs::read_to_string(azero_seed_file)?);
+        pair_from_secret_uri(seed.trim())?
+    } else {
+        let seed = seed_from_env.expect("Checked above");
+        pair_from_secret_uri(seed.trim())?
+    };
+
+    Ok(KeyPair::new(pair))
+}
+
+fn pair_from_secret_uri(secret_uri: &str) -> Result<RawKeyPair, AzeroKeyError> {
+    RawKeyPair::from_string(secret_uri, None).map_err(|_| AzeroKeyError::InvalidSecret)
+}
+
+fn decrypt_polkadot_js_keystore(
+    keystore: &str,
+    password: &str,
+) -> Result<RawKeyPair, AzeroKeyError> {
+    let keystore: PolkadotJsKeystore = serde_json::from_str(keystore)?;
+
+    if !keystore.encoding.content.iter().any(|c| c == "pkcs8")
+        || !keystore.encoding.content.iter().any(|c| c == "sr25519")
+    {
+        return Err(AzeroKeyError::UnsupportedKeystore(format!(
+            "content {:?}",
+            keystore.encoding.content
+        )));
+    }
+    if !keystore.encoding.kind.iter().any(|t| t == "scrypt")
+        || !keystore
+            .encoding
+            .kind
+            .iter()
+            .any(|t| t == "xsalsa20-poly1305")
+    {
+        return Err(AzeroKeyError::UnsupportedKeystore(format!(
+            "encryption {:?}",
+            keystore.encoding.kind
+        )));
+    }
+
+    let encoded = BASE64.decode(keystore.encoded.as_bytes())?;
+    let header_length = SCRYPT_SALT_LENGTH + SCRYPT_PARAMS_LENGTH + NONCE_LENGTH;
+    if encoded.len() <= header_length {
+        return Err(AzeroKeyError::UnsupportedKeystore(
+            "encoded data too short".to_string(),
+        ));
+    }
+
+    let (salt, rest) = encoded.split_at(SCRYPT_SALT_LENGTH);
+    let (params, rest) = rest.split_at(SCRYPT_PARAMS_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+
+    let read_u32 = |i: usize| u32::from_le_bytes(params[4 * i..4 * i + 4].try_into().unwrap());
+    let (n, p, r) = (read_u32(0), read_u32(1), read_u32(2));
+    if !n.is_power_of_two() {
+        return Err(AzeroKeyError::UnsupportedKeystore(format!(
+            "scrypt N = {n}"
+        )));
+    }
+    let params = ScryptParams::new(n.trailing_zeros() as u8, r, p, 32)
+        .map_err(|why| AzeroKeyError::UnsupportedKeystore(why.to_string()))?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt(password.as_bytes(), salt, &params, key.as_mut())
+        .map_err(|why| AzeroKeyError::UnsupportedKeystore(why.to_string()))?;
+
+    let plaintext = Zeroizing::new(
+        XSalsa20Poly1305::new(key.as_ref().into())
+            .decrypt(nonce.into(), ciphertext)
+            .map_err(|_| AzeroKeyError::Decryption)?,
+    );
+
+    secret_from_pkcs8(&plaintext)
+}
+
+fn secret_from_pkcs8(pkcs8: &[u8]) -> Result<RawKeyPair, AzeroKeyError> {
+    let secret_start = PKCS8_HEADER.len();
+    let divider_start = secret_start + SECRET_KEY_LENGTH;
+    let public_start = divider_start + PKCS8_DIVIDER.len();
+
+    if pkcs8.len() != public_start + PUBLIC_KEY_LENGTH
+        || pkcs8[..secret_start] != PKCS8_HEADER
+        || pkcs8[divider_start..public_start] != PKCS8_DIVIDER
+    {
+        return Err(AzeroKeyError::InvalidSecret);
+    }
+
+    // polkadot-js stores the secret key in the ed25519-compatible format, where the scalar
+    // is multiplied by the cofactor. Schnorrkel's canonical encoding expects it divided back.
+    let mut secret = Zeroizing::new([0u8; SECRET_KEY_LENGTH]);
+    secret.copy_from_slice(&pkcs8[secret_start..divider_start]);
+    divide_scalar_by_cofactor(&mut secret[..32]);
+
+    let pair =
+        RawKeyPair::from_seed_slice(secret.as_ref()).map_err(|_| AzeroKeyError::InvalidSecret)?;
+
+    if pair.public().0 != pkcs8[public_start..] {
+        return Err(AzeroKeyError::InvalidSecret);
+    }
+
+    Ok(pair)
+}
+
+fn divide_scalar_by_cofactor(scalar: &mut [u8]) {
+    let mut low = 0u8;
+    for byte in scalar.iter_mut().rev() {
+        let remainder = *byte & 0b0000_0111;
+        *byte >>= 3;
+        *byte += low;
+        low = remainder << 5;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `//Alice` in the polkadot-js export format, encrypted with the password `most-relayer`. Scrypt N is lowered from
+    // the polkadot-js default to keep the test fast, it is read from the keystore anyway.
+    const ALICE_KEYSTORE: &str = r#"{
+        "encoded": "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8ABAAAAQAAAAgAAABkZWZnaGlqa2xtbm9wcXJzdHV2d3h5envoGbxRVa7a6twrPX7a9h4Tfg0+hKyrHFn2oHbh9OtWfcpy6jFIkz4oeUDMZb4kv28VNfIVGuA6w5jtW2BEUX6oUZGx3egShS3ibJG8o7NxjrCt5/y7B4iB/6LwAGklzzhpVAWDfFPPq5flD6RORxuLt8fxFxfWQ23+eNtHi6vG9hT/9cMX",
+        "encoding": {
+            "content": ["pkcs8", "sr25519"],
+            "type": ["scrypt", "xsalsa20-poly1305"],
+            "version": "3"
+        },
+        "address": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+        "meta": { "genesisHash": "", "name": "alice", "whenCreated": 1700000000000 }
+    }"#;
+    const ALICE_PUBLIC_KEY: &str =
+        "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+
+    #[test]
+    fn decrypts_polkadot_js_keystore() {
+        let pair = decrypt_polkadot_js_keystore(ALICE_KEYSTORE, "most-relayer")
+            .expect("Keystore can be decrypted");
+
+        assert_eq!(hex::encode(pair.public().0), ALICE_PUBLIC_KEY);
+    }
+
+    #[test]
+    fn rejects_wrong_keystore_password() {
+        assert!(matches!(
+            decrypt_polkadot_js_keystore(ALICE_KEYSTORE, "wrong password"),
+            Err(AzeroKeyError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn divides_scalar_by_cofactor_across_bytes() {
+        let mut scalar = [0u8; 32];
+        scalar[0] = 8;
+        scalar[1] = 1;
+        scalar[31] = 0x40;
+
+        divide_scalar_by_cofactor(&mut scalar);
+
+        let mut expected = [0u8; 32];
+        expected[0] = 1 + (1 << 5);
+        expected[31] = 0x08;
+        assert_eq!(scalar, expected);
+    }
+}
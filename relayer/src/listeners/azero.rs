@@ -1,39 +1,49 @@
-use std::{collections::BTreeSet, sync::Arc};
+use std::{collections::BTreeSet, sync::Arc, time::Instant};
 
-use aleph_client::{
-    contract::event::{BlockDetails, ContractEvent},
-    utility::BlocksApi,
-    AsConnection,
-};
+use aleph_client::{utility::BlocksApi, AsConnection};
 use ethers::{
-    abi::{self, EncodePackedError, Token},
+    abi::EncodePackedError,
     core::types::Address,
     prelude::{ContractCall, ContractError},
-    providers::{Middleware, ProviderError},
-    utils::keccak256,
+    providers::{Middleware, PendingTransaction, ProviderError},
+    types::{BlockNumber, TransactionReceipt, U256},
 };
+use futures::{stream::BoxStream, FutureExt, StreamExt};
 use log::{debug, error, info, warn};
-use redis::{aio::Connection as RedisConnection, RedisError};
+use most_client::{
+    azero::{
+        AzeroContractError, AzeroMostEvent, CrosschainTransferRequest, MostInstance,
+        RequestProcessed, WeightLimits,
+    },
+    eth::{Most, MostRevert},
+};
+use shared::CrosschainRequest;
 use subxt::utils::H256;
 use thiserror::Error;
 use tokio::{
     sync::{Mutex, OwnedSemaphorePermit, Semaphore},
-    task::JoinHandle,
-    time::{sleep, Duration},
+    task::{JoinError, JoinSet},
+    time::{sleep, timeout, Duration},
 };
 
 use crate::{
-    config::Config,
+    checkpoints::{
+        prune_finished_requests, read_first_unprocessed_block_number, read_request_state,
+        write_last_processed_block, write_request_state, CheckpointError, CheckpointStore,
+        FinishedRequests, RequestState,
+    },
+    config::{Config, FinalityPolicy},
     connections::{
         azero::SignedAzeroWsConnection,
-        eth::{EthConnection, EthConnectionError, SignedEthConnection},
-        redis_helpers::{read_first_unprocessed_block_number, write_last_processed_block},
-    },
-    contracts::{
-        get_request_event_data, AzeroContractError, CrosschainTransferRequestData, Most,
-        MostInstance,
+        eth::{
+            is_nonce_error, is_underpriced_error, EthConnection, EthConnectionError, FeeStrategy,
+            Fees, NonceManager, SignedEthConnection,
+        },
     },
+    health,
     listeners::eth::{get_next_finalized_block_number_eth, ETH_BLOCK_PROD_TIME_SEC},
+    metrics::{self, Chain, Direction, SkipReason},
+    shutdown::{self, ShutdownSignal},
 };
 
 #[derive(Debug, Error)]
@@ -67,26 +77,73 @@ pub enum AzeroListenerError {
     #[error("no block found")]
     BlockNotFound,
 
-    #[error("tx was not present in any block or mempool after the maximum number of retries")]
-    TxNotPresentInBlockOrMempool,
-
-    #[error("missing data from event")]
-    MissingEventData(String),
-
     #[error("error when creating an ABI data encoding")]
     AbiEncode(#[from] EncodePackedError),
 
-    #[error("redis connection error")]
-    Redis(#[from] RedisError),
+    #[error("checkpoint store error")]
+    Checkpoint(#[from] CheckpointError),
+
+    #[error("event handler task failed")]
+    Join(#[from] JoinError),
+
+    #[error("tx is no longer included in the chain")]
+    TxDropped(H256),
+
+    #[error("vote tx was reverted")]
+    VoteReverted(H256, MostRevert),
+
+    #[error("vote would revert")]
+    DryRunReverted(MostRevert),
+
+    #[error(
+        "estimated gas of {gas_estimate} with the safety margin exceeds the limit of {gas_limit}"
+    )]
+    GasLimitExceeded { gas_estimate: U256, gas_limit: U256 },
+
+    #[error("vote tx is not mined with fees at the cap")]
+    FeeCapReached(H256),
 
     #[error("unexpected error")]
     Unexpected,
 }
 
+impl AzeroListenerError {
+    /// Fatal errors stop the relayer, all others only restart the listener.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::FromHex(_) | Self::AbiEncode(_) | Self::Unexpected => true,
+            Self::AzeroContract(why) => why.is_fatal(),
+            Self::Checkpoint(why) => why.is_fatal(),
+            _ => false,
+        }
+    }
+
+    // Why the destination contract rejects the vote, if that is what the error is about.
+    fn revert(&self) -> Option<&MostRevert> {
+        match self {
+            Self::VoteReverted(_, revert) | Self::DryRunReverted(revert) => Some(revert),
+            _ => None,
+        }
+    }
+
+    // Whether voting again on the request cannot help, so it should be dead-lettered for an operator. The gas
+    // estimate does not change between attempts. A vote stuck at the fee cap is given up as well, as sending it again
+    // would only queue another tx behind the stuck one.
+    fn is_permanent(&self) -> bool {
+        match self {
+            Self::GasLimitExceeded { .. } | Self::FeeCapReached(_) => true,
+            _ => self.revert().is_some_and(|revert| !revert.is_retriable()),
+        }
+    }
+}
+
 const ALEPH_LAST_BLOCK_KEY: &str = "alephzero_last_known_block_number";
 const ALEPH_BLOCK_PROD_TIME_SEC: u64 = 1;
-// This is more than the maximum number of send_request calls than will fit into the block (execution time)
-const ALEPH_MAX_REQUESTS_PER_BLOCK: usize = 50;
+const ALEPH_MAX_RPC_FAILURES: u32 = 5;
+// If no finalized head is pushed for this long, the subscription is assumed to be broken.
+const ALEPH_FINALIZED_HEADS_TIMEOUT_SEC: u64 = 60;
+// More requests than this in a single block are unusual, though they are still all handled.
+const ALEPH_LARGE_BLOCK_REQUESTS: usize = 50;
 
 pub struct AlephZeroListener;
 
@@ -95,46 +152,56 @@ impl AlephZeroListener {
         config: Arc<Config>,
         azero_connection: Arc<SignedAzeroWsConnection>,
         eth_connection: Arc<SignedEthConnection>,
-        redis_connection: Arc<Mutex<RedisConnection>>,
+        checkpoint_store: Arc<dyn CheckpointStore>,
+        mut shutdown: ShutdownSignal,
     ) -> Result<(), AzeroListenerError> {
         let Config {
-            azero_contract_metadata,
             azero_contract_address,
             azero_max_event_handler_tasks,
-            name,
             default_sync_from_block_azero,
             ..
         } = &*config;
 
         let pending_blocks: Arc<Mutex<BTreeSet<u32>>> = Arc::new(Mutex::new(BTreeSet::new()));
+        // Requests from the blocks that have been handled, whose records are pruned once the checkpoint passes them.
+        let finished_requests = Arc::new(Mutex::new(FinishedRequests::default()));
+        // Tasks waiting for all events from a block to be handled. Dropping the set (e.g. when the listener fails)
+        // aborts them together with their event handlers.
+        let mut block_tasks = JoinSet::new();
         let event_handler_tasks_semaphore =
             Arc::new(Semaphore::new(*azero_max_event_handler_tasks));
+        metrics::watch_event_handler_semaphore(&event_handler_tasks_semaphore);
+        let nonces = Arc::new(NonceManager::new(eth_connection.clone()));
 
         let most_instance = MostInstance::new(
             azero_contract_address,
-            azero_contract_metadata,
-            config.azero_ref_time_limit,
-            config.azero_proof_size_limit,
+            WeightLimits {
+                ref_time_limit: config.azero_ref_time_limit,
+                proof_size_limit: config.azero_proof_size_limit,
+                gas_safety_margin_percent: config.gas_safety_margin_percent,
+            },
         )?;
         let mut first_unprocessed_block_number = read_first_unprocessed_block_number(
-            name.clone(),
-            ALEPH_LAST_BLOCK_KEY.to_string(),
-            redis_connection.clone(),
+            checkpoint_store.clone(),
+            ALEPH_LAST_BLOCK_KEY,
             *default_sync_from_block_azero,
         )
-        .await;
+        .await?;
+
+        health::listener_advanced(Chain::AlephZero);
+
+        let mut finalized_heads = FinalizedHeads::new(azero_connection.clone());
 
         // Add the first block number to the set of pending blocks.
         add_to_pending(first_unprocessed_block_number, pending_blocks.clone()).await;
 
         // Main AlephZero event loop
-        loop {
+        'listen: loop {
             // Query for the next unknowns finalized block number, if not present we wait for it.
-            let to_block = get_next_finalized_block_number_azero(
-                azero_connection.clone(),
-                first_unprocessed_block_number,
-            )
-            .await;
+            let to_block = tokio::select! {
+                to_block = finalized_heads.next(first_unprocessed_block_number) => to_block?,
+                _ = shutdown::requested(&mut shutdown) => break 'listen,
+            };
 
             info!(
                 "Processing events from blocks {} - {}",
@@ -143,6 +210,11 @@ impl AlephZeroListener {
 
             // Process events from the next unknown finalized block.
             for block_number in first_unprocessed_block_number..=to_block {
+                // Stop fetching new blocks, the ones already being processed are drained below.
+                if shutdown::is_requested(&shutdown) {
+                    break 'listen;
+                }
+
                 // Add the next block number now, so that there is always some block number in the set.
                 add_to_pending(block_number + 1, pending_blocks.clone()).await;
 
@@ -160,29 +232,64 @@ impl AlephZeroListener {
                     .events()
                     .await?;
 
-                let filtered_events = most_instance.filter_events(
-                    events,
-                    BlockDetails {
-                        block_number,
-                        block_hash,
-                    },
-                );
+                let filtered_events = most_instance.filter_events(events);
+                let decoded_at = Instant::now();
 
                 handle_events(
                     config.clone(),
                     eth_connection.clone(),
+                    nonces.clone(),
                     filtered_events,
+                    decoded_at,
                     block_number,
                     pending_blocks.clone(),
-                    redis_connection.clone(),
+                    finished_requests.clone(),
+                    checkpoint_store.clone(),
                     event_handler_tasks_semaphore.clone(),
+                    &mut block_tasks,
                 )
-                .await?;
+                .await;
+                health::listener_advanced(Chain::AlephZero);
+
+                // Surface errors from blocks that are done processing, so that the listener can be restarted.
+                while let Some(Some(result)) = block_tasks.join_next().now_or_never() {
+                    result??;
+                }
             }
 
             // Update the last block number.
             first_unprocessed_block_number = to_block + 1;
         }
+
+        info!(
+            "Waiting for events from {} blocks to be handled before shutting down",
+            block_tasks.len()
+        );
+        while let Some(result) = block_tasks.join_next().await {
+            result??;
+        }
+
+        // Every block task has already written its checkpoint, but the last write could have been skipped
+        // if the lowest pending block was never started.
+        let earliest_still_pending = *pending_blocks
+            .lock()
+            .await
+            .first()
+            .expect("There should always be a pending block in the set");
+        if let Some(last_processed_block) = earliest_still_pending.checked_sub(1) {
+            write_last_processed_block(
+                checkpoint_store.clone(),
+                ALEPH_LAST_BLOCK_KEY,
+                last_processed_block,
+            )
+            .await?;
+            metrics::set_last_processed_block(Chain::AlephZero, last_processed_block);
+            prune_finished_requests(checkpoint_store, &finished_requests, last_processed_block)
+                .await;
+        }
+        info!("AlephZero listener stopped at block {earliest_still_pending}");
+
+        Ok(())
     }
 }
 
@@ -191,20 +298,57 @@ async fn add_to_pending(block_number: u32, pending_blocks: Arc<Mutex<BTreeSet<u3
     pending_blocks.insert(block_number);
 }
 
-// handle all events present in one block
+// Handles all events present in one block. It cannot fail on its own, no matter how many events there are, only
+// the spawned handlers can.
+#[allow(clippy::too_many_arguments)]
 async fn handle_events(
     config: Arc<Config>,
     eth_connection: Arc<SignedEthConnection>,
-    events: Vec<ContractEvent>,
+    nonces: Arc<NonceManager>,
+    events: Vec<AzeroMostEvent>,
+    decoded_at: Instant,
     block_number: u32,
     pending_blocks: Arc<Mutex<BTreeSet<u32>>>,
-    redis_connection: Arc<Mutex<RedisConnection>>,
+    finished_requests: Arc<Mutex<FinishedRequests>>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
     event_handler_tasks_semaphore: Arc<Semaphore>,
-) -> Result<(), AzeroListenerError> {
-    let mut event_tasks = Vec::new();
+    block_tasks: &mut JoinSet<Result<(), AzeroListenerError>>,
+) {
+    // Events beyond the free permits wait for earlier ones to be handled, so a large block only slows the listener down.
+    let requests = events
+        .iter()
+        .filter(|event| matches!(event, AzeroMostEvent::CrosschainTransferRequest(_)))
+        .count();
+    if requests >= ALEPH_LARGE_BLOCK_REQUESTS {
+        warn!(
+            "Block {block_number} has {requests} requests, they are queued until event handlers are free"
+        );
+        metrics::large_block(Chain::AlephZero);
+    }
+
+    let request_hashes = events
+        .iter()
+        .filter_map(|event| match event {
+            AzeroMostEvent::CrosschainTransferRequest(event) => Some(
+                CrosschainRequest {
+                    committee_id: event.committee_id,
+                    dest_token_address: event.dest_token_address,
+                    amount: event.amount,
+                    dest_receiver_address: event.dest_receiver_address,
+                    request_nonce: event.request_nonce,
+                }
+                .eth_hash(),
+            ),
+            _ => None,
+        })
+        .collect();
+
+    let mut event_tasks = JoinSet::new();
     for event in events {
         let config = config.clone();
         let eth_connection = eth_connection.clone();
+        let nonces = nonces.clone();
+        let checkpoint_store = checkpoint_store.clone();
         let permit = event_handler_tasks_semaphore
             .clone()
             .acquire_owned()
@@ -212,158 +356,663 @@ async fn handle_events(
             .expect("Failed to acquire semaphore permit");
 
         // Spawn a new task for handling each event.
-        event_tasks.push(tokio::spawn(async move {
-            handle_event(config, eth_connection, event, permit)
-                .await
-                .expect("Event handler failed");
-        }));
-        if event_tasks.len() >= ALEPH_MAX_REQUESTS_PER_BLOCK {
-            panic!("Too many send_request calls in one block: our benchmark is outdated.");
-        }
+        event_tasks.spawn(handle_event(
+            config,
+            eth_connection,
+            nonces,
+            checkpoint_store,
+            event,
+            decoded_at,
+            permit,
+        ));
     }
 
-    tokio::spawn(async move {
-        handle_processed_block(
-            config.clone(),
-            block_number,
-            event_tasks,
-            pending_blocks.clone(),
-            redis_connection.clone(),
-        )
-        .await
-        .expect(
-            "Failed to wait for event handler tasks or to update the last processed block number.",
-        );
-    });
-    Ok(())
+    block_tasks.spawn(handle_processed_block(
+        block_number,
+        request_hashes,
+        event_tasks,
+        pending_blocks,
+        finished_requests,
+        checkpoint_store,
+    ));
 }
 
 async fn handle_event(
     config: Arc<Config>,
     eth_connection: Arc<SignedEthConnection>,
-    event: ContractEvent,
+    nonces: Arc<NonceManager>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    event: AzeroMostEvent,
+    decoded_at: Instant,
     _permit: OwnedSemaphorePermit,
 ) -> Result<(), AzeroListenerError> {
     let Config {
-        committee_id,
         eth_contract_address,
-        eth_tx_min_confirmations,
-        eth_tx_submission_retries,
         ..
     } = &*config;
-    if let Some(name) = &event.name {
-        if name.eq("CrosschainTransferRequest") {
-            let data = event.data;
-
-            // decode event data
-            let CrosschainTransferRequestData {
-                dest_token_address,
-                amount,
-                dest_receiver_address,
-                request_nonce,
-            } = get_request_event_data(&data)?;
+    if let AzeroMostEvent::CrosschainTransferRequest(CrosschainTransferRequest {
+        committee_id,
+        dest_token_address,
+        amount,
+        dest_receiver_address,
+        request_nonce,
+    }) = event
+    {
+        info!(
+            "Decoded event data: [committee_id: {committee_id}, dest_token_address: 0x{}, amount: {amount}, dest_receiver_address: 0x{}, request_nonce: {request_nonce}]",
+            hex::encode(dest_token_address),
+            hex::encode(dest_receiver_address)
+        );
 
-            info!(
-                "Decoded event data: [dest_token_address: 0x{}, amount: {amount}, dest_receiver_address: 0x{}, request_nonce: {request_nonce}]", 
-                hex::encode(dest_token_address),
-                hex::encode(dest_receiver_address)
-            );
+        let address = eth_contract_address.parse::<Address>()?;
+        let contract = Most::new(address, eth_connection.clone());
 
-            // NOTE: for some reason, ethers-rs's `encode_packed` does not properly encode the data
-            // (it does not pad uint to 32 bytes, but uses the actual number of bytes required to store the value)
-            // so we use `abi::encode` instead (it only differs for signed and dynamic size types, which we don't use here)
-            let bytes = abi::encode(&[
-                Token::Uint((*committee_id).into()),
-                Token::FixedBytes(dest_token_address.to_vec()),
-                Token::Uint(amount.into()),
-                Token::FixedBytes(dest_receiver_address.to_vec()),
-                Token::Uint(request_nonce.into()),
-            ]);
-
-            debug!("ABI event encoding: 0x{}", hex::encode(bytes.clone()));
-
-            let request_hash = keccak256(bytes);
-
-            info!("hashed event encoding: 0x{}", hex::encode(request_hash));
-
-            let address = eth_contract_address.parse::<Address>()?;
-            let contract = Most::new(address, eth_connection.clone());
-
-            // forward transfer & vote
-            let call: ContractCall<SignedEthConnection, ()> = contract.receive_request(
-                request_hash,
-                (*committee_id).into(),
-                dest_token_address,
-                amount.into(),
-                dest_receiver_address,
-                request_nonce.into(),
+        // The committee id is taken from the event, so that we follow committee rotations on the source chain.
+        let guardian = eth_connection.address();
+        if !contract
+            .is_in_committee(committee_id.into(), guardian)
+            .call()
+            .await?
+        {
+            warn!(
+                "Guardian {guardian:?} is not a member of committee {committee_id} targeted by the request with nonce {request_nonce}, skipping the vote"
             );
+            return Ok(());
+        }
+
+        let request = CrosschainRequest {
+            committee_id,
+            dest_token_address,
+            amount,
+            dest_receiver_address,
+            request_nonce,
+        };
+
+        debug!(
+            "ABI event encoding: 0x{}",
+            hex::encode(request.eth_encoding())
+        );
+
+        let request_hash = request.eth_hash();
+
+        info!("hashed event encoding: 0x{}", hex::encode(request_hash));
+
+        let result = vote(
+            &config,
+            eth_connection,
+            &nonces,
+            checkpoint_store.clone(),
+            &contract,
+            request,
+            request_hash,
+            decoded_at,
+        )
+        .await;
+
+        match &result {
+            // Sending the vote again would only waste fees, so the request is dead-lettered for an operator.
+            Err(why) if why.is_permanent() => {
+                error!(
+                    "Vote on request 0x{} cannot succeed: {why:?}, giving up on the request",
+                    hex::encode(request_hash)
+                );
+                metrics::vote_failed(Direction::AlephZeroToEthereum);
+                metrics::vote_dead_lettered(Direction::AlephZeroToEthereum);
+                metrics::request_abandoned(request_hash);
+                write_request_state(
+                    checkpoint_store,
+                    &request_hash,
+                    &RequestState::DeadLettered {
+                        reason: format!("{why:?}"),
+                    },
+                )
+                .await?;
+                return Ok(());
+            }
+            Err(why) => {
+                metrics::vote_failed(Direction::AlephZeroToEthereum);
+                write_request_state(
+                    checkpoint_store,
+                    &request_hash,
+                    &RequestState::Failed {
+                        reason: format!("{why:?}"),
+                    },
+                )
+                .await?;
+            }
+            Ok(()) => {}
+        }
 
+        return result;
+    }
+
+    if let AzeroMostEvent::RequestProcessed(RequestProcessed { request_hash, .. }) = event {
+        // Completes a transfer from Ethereum, which is only measured here.
+        metrics::request_processed(Direction::EthereumToAlephZero, request_hash);
+    }
+    Ok(())
+}
+
+// Sends this guardian's vote on the request to Ethereum and waits for it to be finalized.
+//
+// Progress is persisted in the checkpoint store, so that after a restart a vote that has already been sent
+// is awaited instead of being sent again.
+#[allow(clippy::too_many_arguments)]
+async fn vote(
+    config: &Config,
+    eth_connection: Arc<SignedEthConnection>,
+    nonces: &NonceManager,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    contract: &Most<SignedEthConnection>,
+    request: CrosschainRequest,
+    request_hash: [u8; 32],
+    decoded_at: Instant,
+) -> Result<(), AzeroListenerError> {
+    let CrosschainRequest {
+        committee_id,
+        dest_token_address,
+        amount,
+        dest_receiver_address,
+        request_nonce,
+    } = request;
+
+    // Set when resuming a vote that has already been sent.
+    let mut pending_tx_hash = None;
+    let mut resubmissions = 0;
+    match read_request_state(checkpoint_store.clone(), &request_hash).await? {
+        Some(RequestState::Finalized { tx_hash }) => {
+            info!(
+                "Vote on request 0x{} has already been finalized in tx {tx_hash:?}, skipping",
+                hex::encode(request_hash)
+            );
+            return Ok(());
+        }
+        Some(RequestState::Submitted {
+            tx_hash,
+            resubmissions: previous_resubmissions,
+        })
+        | Some(RequestState::Confirmed {
+            tx_hash,
+            resubmissions: previous_resubmissions,
+            ..
+        }) => {
             info!(
-                "Sending tx with request nonce {} to the Ethereum network and waiting for {} confirmations",
-                request_nonce,
-                eth_tx_min_confirmations
+                "Resuming the vote on request 0x{} sent in tx {tx_hash:?}",
+                hex::encode(request_hash)
             );
+            pending_tx_hash = Some(tx_hash);
+            resubmissions = previous_resubmissions;
+        }
+        Some(RequestState::Skipped { reason }) | Some(RequestState::DeadLettered { reason }) => {
+            info!(
+                "Vote on request 0x{} has been skipped: {reason}",
+                hex::encode(request_hash)
+            );
+            return Ok(());
+        }
+        Some(RequestState::Discovered) | Some(RequestState::Failed { .. }) => {}
+        None => {
+            write_request_state(
+                checkpoint_store.clone(),
+                &request_hash,
+                &RequestState::Discovered,
+            )
+            .await?;
+        }
+    }
+
+    // forward transfer & vote
+    let call: ContractCall<SignedEthConnection, ()> = contract.receive_request(
+        request_hash,
+        committee_id.into(),
+        dest_token_address,
+        amount.into(),
+        dest_receiver_address,
+        request_nonce.into(),
+    );
 
-            // This shouldn't fail unless there is something wrong with our config.
-            // NOTE: this does not check whether the actual tx reverted on-chain. Reverts are only checked on dry-run.
-            let tx_hash = call
-                .gas(config.eth_gas_limit)
-                .send()
+    // A vote whose tx is dropped by a reorg or evicted from the mempool is sent again, unless the request has
+    // been processed without it in the meantime.
+    let tx_hash = loop {
+        let tx_hash = match pending_tx_hash.take() {
+            Some(tx_hash) => tx_hash,
+            None => {
+                // Sending a vote the contract does not need would only emit `ProcessedRequestSigned` or revert.
+                let skip_reason = if contract.processed_requests(request_hash).call().await? {
+                    Some(SkipReason::AlreadyProcessed)
+                } else if contract
+                    .has_signed_request(eth_connection.address(), request_hash)
+                    .call()
+                    .await?
+                {
+                    Some(SkipReason::AlreadySigned)
+                } else {
+                    None
+                };
+                if let Some(skip_reason) = skip_reason {
+                    return skip_vote(checkpoint_store, request_hash, skip_reason).await;
+                }
+
+                info!(
+                    "Sending tx with request nonce {} to the Ethereum network and waiting for {} confirmations",
+                    request_nonce,
+                    config.eth_tx_confirmations()
+                );
+                metrics::request_discovered(request_hash, decoded_at);
+
+                // This shouldn't fail unless there is something wrong with our config.
+                // Whether the tx reverted on-chain is checked once it is confirmed.
+                submit_vote(
+                    config,
+                    &eth_connection,
+                    nonces,
+                    checkpoint_store.clone(),
+                    &call,
+                    request_hash,
+                    resubmissions,
+                )
                 .await?
-                .confirmations(*eth_tx_min_confirmations)
-                .retries(*eth_tx_submission_retries)
+            }
+        };
+
+        match await_vote(
+            config,
+            eth_connection.clone(),
+            checkpoint_store.clone(),
+            &call,
+            request_hash,
+            tx_hash,
+            resubmissions,
+        )
+        .await
+        {
+            Ok(()) => break tx_hash,
+            // Our vote is already counted, e.g. from a tx that was thought to be dropped.
+            Err(why) if why.revert() == Some(&MostRevert::AlreadySigned) => {
+                return skip_vote(checkpoint_store, request_hash, SkipReason::AlreadySigned).await;
+            }
+            Err(AzeroListenerError::TxDropped(tx_hash)) => {
+                // The dropped tx may have left a gap in the nonces.
+                nonces.resync();
+                resubmissions += 1;
+                warn!(
+                    "Vote {tx_hash:?} on request 0x{} has been dropped, sending it again (resubmission {resubmissions})",
+                    hex::encode(request_hash)
+                );
+                metrics::vote_resubmitted(Direction::AlephZeroToEthereum);
+            }
+            Err(why) => return Err(why),
+        }
+    };
+
+    write_request_state(
+        checkpoint_store,
+        &request_hash,
+        &RequestState::Finalized { tx_hash },
+    )
+    .await?;
+    metrics::vote_succeeded(Direction::AlephZeroToEthereum);
+
+    Ok(())
+}
+
+async fn skip_vote(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    request_hash: [u8; 32],
+    reason: SkipReason,
+) -> Result<(), AzeroListenerError> {
+    info!(
+        "Not voting on request 0x{}: {reason:?}",
+        hex::encode(request_hash)
+    );
+    metrics::vote_skipped(Direction::AlephZeroToEthereum, reason);
+    metrics::request_abandoned(request_hash);
+    write_request_state(
+        checkpoint_store,
+        &request_hash,
+        &RequestState::Skipped {
+            reason: format!("{reason:?}"),
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+// Sends the vote and waits for it to be mined, replacing it with higher fees whenever it has been stuck for
+// `eth_fee_bump_blocks` blocks. Returns the hash of the tx that has been mined, or fails with `FeeCapReached` once
+// the tx has been stuck for `eth_fee_cap_wait_blocks` blocks with fees at the cap.
+//
+// Nonces are read from the node again after a failure to send, as the reserved one is either taken by another tx
+// or left unused.
+async fn submit_vote(
+    config: &Config,
+    eth_connection: &SignedEthConnection,
+    nonces: &NonceManager,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    call: &ContractCall<SignedEthConnection, ()>,
+    request_hash: [u8; 32],
+    resubmissions: u32,
+) -> Result<H256, AzeroListenerError> {
+    let Config {
+        eth_tx_submission_retries,
+        eth_gas_limit,
+        gas_safety_margin_percent,
+        eth_max_priority_fee_per_gas,
+        eth_max_fee_per_gas_cap,
+        eth_fee_bump_blocks,
+        eth_fee_bump_percent,
+        eth_fee_cap_wait_blocks,
+        ..
+    } = config;
+    let fee_strategy = FeeStrategy {
+        max_priority_fee_per_gas: eth_max_priority_fee_per_gas.map(U256::from),
+        max_fee_per_gas_cap: (*eth_max_fee_per_gas_cap).into(),
+        bump_percent: *eth_fee_bump_percent,
+    };
+
+    // Dry-run the vote, so that a vote bound to revert is not sent and the gas limit fits what it needs.
+    let gas_estimate =
+        call.estimate_gas()
+            .await
+            .map_err(|why| match MostRevert::from_contract_error(&why) {
+                Some(revert) => AzeroListenerError::DryRunReverted(revert),
+                None => why.into(),
+            })?;
+    let gas_limit =
+        gas_estimate.saturating_mul(100u64.saturating_add(*gas_safety_margin_percent).into()) / 100;
+    if gas_limit > (*eth_gas_limit).into() {
+        return Err(AzeroListenerError::GasLimitExceeded {
+            gas_estimate,
+            gas_limit: (*eth_gas_limit).into(),
+        });
+    }
+
+    let mut fees = fee_strategy.fees(eth_connection).await?;
+
+    let mut attempts = 0;
+    let (nonce, mut tx_hash) = loop {
+        let reservation = nonces.next().await?;
+        let nonce = reservation.nonce();
+        match send_vote_tx(call, gas_limit, nonce, fees).await {
+            Ok(tx_hash) => break (nonce, tx_hash),
+            Err(why) => {
+                nonces.resync();
+                if attempts < *eth_tx_submission_retries && is_nonce_error(&why) {
+                    attempts += 1;
+                    warn!("Nonce {nonce} is already used, retrying with a fresh one");
+                    continue;
+                }
+                return Err(why.into());
+            }
+        }
+    };
+    metrics::vote_submitted(Direction::AlephZeroToEthereum);
+
+    // All txs sent with the nonce, as any of them may be the one that gets mined.
+    let mut tx_hashes = vec![tx_hash];
+    let mut sent_at_block = eth_connection.get_block_number().await?;
+    // Block at which the fees have been found to be at the cap, if they are.
+    let mut capped_at_block = None;
+    loop {
+        write_request_state(
+            checkpoint_store.clone(),
+            &request_hash,
+            &RequestState::Submitted {
+                tx_hash,
+                resubmissions,
+            },
+        )
+        .await?;
+
+        loop {
+            sleep(Duration::from_secs(ETH_BLOCK_PROD_TIME_SEC)).await;
+
+            for &sent_tx_hash in &tx_hashes {
+                if eth_connection
+                    .get_transaction_receipt(sent_tx_hash)
+                    .await?
+                    .is_some()
+                {
+                    if sent_tx_hash != tx_hash {
+                        write_request_state(
+                            checkpoint_store.clone(),
+                            &request_hash,
+                            &RequestState::Submitted {
+                                tx_hash: sent_tx_hash,
+                                resubmissions,
+                            },
+                        )
+                        .await?;
+                    }
+                    return Ok(sent_tx_hash);
+                }
+            }
+
+            let block_number = eth_connection.get_block_number().await?;
+            if block_number >= sent_at_block + *eth_fee_bump_blocks {
+                sent_at_block = block_number;
+                break;
+            }
+        }
+
+        let mut is_known = false;
+        for &sent_tx_hash in &tx_hashes {
+            if eth_connection
+                .get_transaction(sent_tx_hash)
                 .await?
-                .ok_or(AzeroListenerError::TxNotPresentInBlockOrMempool)?
-                .transaction_hash;
+                .is_some()
+            {
+                is_known = true;
+                break;
+            }
+        }
+        if !is_known {
+            return Err(AzeroListenerError::TxDropped(tx_hash));
+        }
 
-            info!("Tx with nonce {request_nonce} has been sent to the Ethereum network: {tx_hash:?} and received {eth_tx_min_confirmations} confirmations.");
+        let at_cap = match fee_strategy.bump(fees) {
+            Some(bumped_fees) => match send_vote_tx(call, gas_limit, nonce, bumped_fees).await {
+                Ok(replacement_tx_hash) => {
+                    info!(
+                        "Replaced vote tx {tx_hash:?} not mined for {eth_fee_bump_blocks} blocks with {replacement_tx_hash:?}, raising the max fee from {} to {} wei and the priority fee from {} to {} wei",
+                        fees.max_fee_per_gas,
+                        bumped_fees.max_fee_per_gas,
+                        fees.max_priority_fee_per_gas,
+                        bumped_fees.max_priority_fee_per_gas
+                    );
+                    metrics::eth_fee_bumped();
+                    fees = bumped_fees;
+                    tx_hash = replacement_tx_hash;
+                    tx_hashes.push(tx_hash);
+                    false
+                }
+                // The node wants a higher raise than the cap allows.
+                Err(why) if is_underpriced_error(&why) => {
+                    warn!("Replacement of vote tx {tx_hash:?} is rejected as underpriced: {why:?}");
+                    true
+                }
+                // The tx may have just been mined, which is noticed above.
+                Err(why) => {
+                    warn!("Cannot replace vote tx {tx_hash:?}: {why:?}");
+                    false
+                }
+            },
+            None => true,
+        };
+        if !at_cap {
+            continue;
+        }
 
-            wait_for_eth_tx_finality(eth_connection, tx_hash).await?;
+        let capped_at_block = *capped_at_block.get_or_insert(sent_at_block);
+        if sent_at_block >= capped_at_block + *eth_fee_cap_wait_blocks {
+            error!(
+                "Vote tx {tx_hash:?} has not been mined for {eth_fee_cap_wait_blocks} blocks with its max fee at the cap of {} wei, giving up on it",
+                fees.max_fee_per_gas
+            );
+            metrics::eth_vote_stuck_at_fee_cap();
+            return Err(AzeroListenerError::FeeCapReached(tx_hash));
         }
+        warn!(
+            "Vote tx {tx_hash:?} has not been mined for {eth_fee_bump_blocks} blocks, but its fees cannot be raised further under the cap of {} wei",
+            fees.max_fee_per_gas
+        );
+    }
+}
+
+async fn send_vote_tx(
+    call: &ContractCall<SignedEthConnection, ()>,
+    gas_limit: U256,
+    nonce: U256,
+    fees: Fees,
+) -> Result<H256, ContractError<SignedEthConnection>> {
+    let mut call = call.clone().gas(gas_limit).nonce(nonce);
+    if let Some(tx) = call.tx.as_eip1559_mut() {
+        tx.max_fee_per_gas = Some(fees.max_fee_per_gas);
+        tx.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+    }
+    let pending_tx = call.send().await?;
+    Ok(*pending_tx)
+}
+
+// Waits for the vote sent in `tx_hash` to be confirmed and then finalized. Fails with `TxDropped` if the tx
+// disappears on the way, and with `VoteReverted` if it is confirmed but reverted.
+#[allow(clippy::too_many_arguments)]
+async fn await_vote(
+    config: &Config,
+    eth_connection: Arc<SignedEthConnection>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    call: &ContractCall<SignedEthConnection, ()>,
+    request_hash: [u8; 32],
+    tx_hash: H256,
+    resubmissions: u32,
+) -> Result<(), AzeroListenerError> {
+    let Config {
+        eth_tx_submission_retries,
+        eth_finality,
+        ..
+    } = config;
+
+    let receipt = PendingTransaction::new(tx_hash, eth_connection.provider())
+        .confirmations(config.eth_tx_confirmations())
+        .retries(*eth_tx_submission_retries)
+        .await?
+        .ok_or(AzeroListenerError::TxDropped(tx_hash))?;
+
+    if let Some(gas_used) = receipt.gas_used {
+        metrics::eth_gas_spent(gas_used, receipt.effective_gas_price.unwrap_or_default());
+    }
+
+    check_vote_receipt(call, &receipt).await?;
+    write_request_state(
+        checkpoint_store,
+        &request_hash,
+        &RequestState::Confirmed {
+            tx_hash,
+            block_hash: receipt.block_hash.unwrap_or_default(),
+            block_number: receipt.block_number.unwrap_or_default().as_u64(),
+            resubmissions,
+        },
+    )
+    .await?;
+
+    info!(
+        "Tx {tx_hash:?} has been sent to the Ethereum network and received {} confirmations.",
+        config.eth_tx_confirmations()
+    );
+
+    wait_for_eth_tx_finality(eth_connection.clone(), *eth_finality, tx_hash).await?;
+
+    // A reorg may have moved the tx to another block since it was confirmed, where it can revert.
+    let final_receipt = eth_connection
+        .provider()
+        .get_transaction_receipt(tx_hash)
+        .await?
+        .ok_or(AzeroListenerError::TxDropped(tx_hash))?;
+    if final_receipt.block_hash != receipt.block_hash {
+        warn!(
+            "Vote tx {tx_hash:?} has been moved by a reorg from block {:?} to {:?}",
+            receipt.block_hash, final_receipt.block_hash
+        );
+        check_vote_receipt(call, &final_receipt).await?;
     }
     Ok(())
 }
 
-// Awaits for all requests from the block to be processed, then updates the last processed block number in Redis.
+// Fails with `VoteReverted` if the receipt shows the vote has been reverted.
+async fn check_vote_receipt(
+    call: &ContractCall<SignedEthConnection, ()>,
+    receipt: &TransactionReceipt,
+) -> Result<(), AzeroListenerError> {
+    if receipt.status == Some(1u64.into()) {
+        return Ok(());
+    }
+
+    // Receipts do not carry the revert reason, so the vote is replayed on top of the parent block.
+    let parent_block_number = receipt
+        .block_number
+        .unwrap_or_default()
+        .saturating_sub(1u64.into());
+    let revert = match call
+        .clone()
+        .block(BlockNumber::Number(parent_block_number))
+        .call()
+        .await
+    {
+        Err(why) => MostRevert::from_contract_error(&why).unwrap_or(MostRevert::Unknown),
+        Ok(()) => MostRevert::Unknown,
+    };
+    let tx_hash = receipt.transaction_hash;
+    warn!("Vote tx {tx_hash:?} has been reverted: {revert}");
+    Err(AzeroListenerError::VoteReverted(tx_hash, revert))
+}
+
+// Awaits for all requests from the block to be processed, then updates the last processed block number in the checkpoint store.
 async fn handle_processed_block(
-    config: Arc<Config>,
     block_number: u32,
-    event_tasks: Vec<JoinHandle<()>>,
+    request_hashes: Vec<[u8; 32]>,
+    mut event_tasks: JoinSet<Result<(), AzeroListenerError>>,
     pending_blocks: Arc<Mutex<BTreeSet<u32>>>,
-    redis_connection: Arc<Mutex<RedisConnection>>,
+    finished_requests: Arc<Mutex<FinishedRequests>>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
 ) -> Result<(), AzeroListenerError> {
-    let Config { name, .. } = &*config;
-
-    // Wait for all event processing tasks to finish.
-    for task in event_tasks {
-        task.await.expect("Event processing task has failed");
+    // Wait for all event processing tasks to finish. If any of them fails, the block stays pending.
+    while let Some(result) = event_tasks.join_next().await {
+        result??;
     }
 
     // Lock the pending blocks set and remove the current block number (as we managed to process all events from it).
     let mut pending_blocks = pending_blocks.lock().await;
     pending_blocks.remove(&block_number);
+    finished_requests
+        .lock()
+        .await
+        .add(block_number, request_hashes);
 
     // Now we know that all blocks before the pending block with the lowest number have been processed.
-    // We can update the last processed block number in Redis.
+    // We can update the last processed block number in the checkpoint store.
     let earliest_still_pending = pending_blocks
         .first()
         .expect("There should always be a pending block in the set");
 
     // Note: `earliest_still_pending` will never be 0
+    let last_processed_block = earliest_still_pending - 1;
     write_last_processed_block(
-        name.clone(),
-        ALEPH_LAST_BLOCK_KEY.to_string(),
-        redis_connection,
-        earliest_still_pending - 1,
+        checkpoint_store.clone(),
+        ALEPH_LAST_BLOCK_KEY,
+        last_processed_block,
     )
     .await?;
+    metrics::set_last_processed_block(Chain::AlephZero, last_processed_block);
+    drop(pending_blocks);
+
+    prune_finished_requests(checkpoint_store, &finished_requests, last_processed_block).await;
 
     Ok(())
 }
 
 pub async fn wait_for_eth_tx_finality(
     eth_connection: Arc<SignedEthConnection>,
+    finality: FinalityPolicy,
     tx_hash: H256,
 ) -> Result<(), AzeroListenerError> {
     info!("Waiting for tx finality: {tx_hash:?}");
@@ -371,7 +1020,7 @@ pub async fn wait_for_eth_tx_finality(
         sleep(Duration::from_secs(ETH_BLOCK_PROD_TIME_SEC)).await;
 
         let finalized_head_number =
-            get_next_finalized_block_number_eth(eth_connection.clone(), 0).await;
+            get_next_finalized_block_number_eth(eth_connection.clone(), finality, 0).await;
 
         match eth_connection.get_transaction(tx_hash).await {
             Ok(Some(tx)) => {
@@ -385,31 +1034,128 @@ pub async fn wait_for_eth_tx_finality(
             Err(err) => {
                 error!("Failed to get tx that should be present: {err}");
             }
-            Ok(None) => panic!("Transaction {tx_hash:?} for which finality we were waiting is no longer included in the chain, aborting..."),
+            Ok(None) => {
+                warn!("Transaction {tx_hash:?} for which finality we were waiting is no longer included in the chain");
+                return Err(AzeroListenerError::TxDropped(tx_hash));
+            }
         };
     }
 }
 
-async fn get_next_finalized_block_number_azero(
+// Finalized block numbers pushed by the node over the websocket, with polling as a fallback while the subscription
+// is broken. It is resubscribed on the next call.
+struct FinalizedHeads {
     azero_connection: Arc<SignedAzeroWsConnection>,
-    not_older_than: u32,
-) -> u32 {
-    loop {
-        match azero_connection.get_finalized_block_hash().await {
-            Ok(hash) => match azero_connection.get_block_number(hash).await {
-                Ok(number_opt) => {
-                    let best_finalized_block_number =
-                        number_opt.expect("Finalized block should have a number.");
-                    if best_finalized_block_number >= not_older_than {
-                        return best_finalized_block_number;
+    subscription: Option<BoxStream<'static, Result<u32, subxt::Error>>>,
+}
+
+impl FinalizedHeads {
+    fn new(azero_connection: Arc<SignedAzeroWsConnection>) -> Self {
+        Self {
+            azero_connection,
+            subscription: None,
+        }
+    }
+
+    // Waits for a finalized block with a number not lower than `not_older_than` and returns the number.
+    async fn next(&mut self, not_older_than: u32) -> Result<u32, AzeroListenerError> {
+        if self.subscription.is_none() {
+            self.subscription = self.subscribe().await;
+        }
+
+        if let Some(subscription) = &mut self.subscription {
+            loop {
+                match timeout(
+                    Duration::from_secs(ALEPH_FINALIZED_HEADS_TIMEOUT_SEC),
+                    subscription.next(),
+                )
+                .await
+                {
+                    Ok(Some(Ok(best_finalized_block_number))) => {
+                        metrics::set_last_finalized_block(
+                            Chain::AlephZero,
+                            best_finalized_block_number,
+                        );
+                        if best_finalized_block_number >= not_older_than {
+                            return Ok(best_finalized_block_number);
+                        }
+                    }
+                    Ok(Some(Err(err))) => {
+                        warn!("Finalized heads subscription has failed: {err}");
+                        break;
+                    }
+                    Ok(None) => {
+                        warn!("Finalized heads subscription has been closed by the node");
+                        break;
+                    }
+                    Err(_) => {
+                        warn!(
+                            "No finalized head received for {ALEPH_FINALIZED_HEADS_TIMEOUT_SEC}s"
+                        );
+                        break;
                     }
                 }
-                Err(err) => {
-                    warn!("Aleph Client error when getting best finalized block number: {err}");
+            }
+            self.subscription = None;
+        }
+
+        info!("Polling for the finalized head until the subscription is restored");
+        Ok(
+            get_next_finalized_block_number_azero(self.azero_connection.clone(), not_older_than)
+                .await?,
+        )
+    }
+
+    async fn subscribe(&self) -> Option<BoxStream<'static, Result<u32, subxt::Error>>> {
+        match self
+            .azero_connection
+            .as_connection()
+            .as_client()
+            .blocks()
+            .subscribe_finalized()
+            .await
+        {
+            Ok(blocks) => Some(
+                blocks
+                    .map(|block| block.map(|block| block.number()))
+                    .boxed(),
+            ),
+            Err(err) => {
+                warn!("Cannot subscribe to finalized heads: {err}");
+                None
+            }
+        }
+    }
+}
+
+// Gives up after `ALEPH_MAX_RPC_FAILURES` consecutive errors, so that the caller can be restarted with a new connection.
+pub async fn get_next_finalized_block_number_azero(
+    azero_connection: Arc<SignedAzeroWsConnection>,
+    not_older_than: u32,
+) -> Result<u32, anyhow::Error> {
+    let mut failures = 0;
+    loop {
+        let result = match azero_connection.get_finalized_block_hash().await {
+            Ok(hash) => azero_connection.get_block_number(hash).await,
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(number_opt) => {
+                failures = 0;
+                let best_finalized_block_number =
+                    number_opt.expect("Finalized block should have a number.");
+                metrics::set_last_finalized_block(Chain::AlephZero, best_finalized_block_number);
+                if best_finalized_block_number >= not_older_than {
+                    return Ok(best_finalized_block_number);
                 }
-            },
+            }
             Err(err) => {
-                warn!("Aleph Client error when getting best finalized block hash: {err}");
+                warn!("Aleph Client error when getting best finalized block number: {err}");
+                failures += 1;
+                if failures >= ALEPH_MAX_RPC_FAILURES {
+                    return Err(err);
+                }
             }
         };
 
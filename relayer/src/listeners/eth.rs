@@ -1,33 +1,53 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt::Debug,
+    sync::Arc,
+    time::Instant,
+};
 
+use aleph_client::{utility::BlocksApi, SignedConnectionApi, TxInfo};
 use ethers::{
     abi::EncodePackedError,
     core::types::Address,
     prelude::{k256::ecdsa::SigningKey, ContractError, SignerMiddleware},
     providers::{Middleware, ProviderError},
     signers::Wallet,
-    types::BlockNumber,
-    utils::keccak256,
+    types::{BlockNumber, H256},
+};
+use futures::FutureExt;
+use log::{debug, error, info, trace, warn};
+use most_client::{
+    azero::{
+        is_retriable_vote_error, AzeroContractError, MostError, MostInstance, VoteBatch,
+        WeightLimits,
+    },
+    eth::{Most, MostEvents, RequestProcessedFilter},
 };
-use log::{debug, info, trace, warn};
-use redis::{aio::Connection as RedisConnection, RedisError};
+use shared::CrosschainRequest;
 use thiserror::Error;
 use tokio::{
-    sync::Mutex,
+    sync::{Mutex, OwnedSemaphorePermit, Semaphore},
+    task::{JoinError, JoinSet},
     time::{sleep, Duration},
 };
 
 use crate::{
-    config::Config,
+    checkpoints::{
+        prune_finished_requests, read_first_unprocessed_block_number, read_request_state,
+        write_last_processed_block, write_request_state, CheckpointError, CheckpointStore,
+        FinishedRequests, RequestState,
+    },
+    config::{Config, FinalityPolicy},
     connections::{
         azero::SignedAzeroWsConnection,
         eth::{EthConnection, SignedEthConnection},
-        redis_helpers::{read_first_unprocessed_block_number, write_last_processed_block},
     },
-    contracts::{
-        AzeroContractError, CrosschainTransferRequestFilter, Most, MostEvents, MostInstance,
+    health,
+    listeners::{
+        azero::get_next_finalized_block_number_azero, eth_subscription::EthLogSubscription,
     },
-    helpers::concat_u8_arrays,
+    metrics::{self, Chain, Direction, SkipReason},
+    shutdown::{self, ShutdownSignal},
 };
 
 #[derive(Debug, Error)]
@@ -49,12 +69,41 @@ pub enum EthListenerError {
     #[error("error when creating an ABI data encoding")]
     AbiEncode(#[from] EncodePackedError),
 
-    #[error("redis connection error")]
-    Redis(#[from] RedisError),
+    #[error("checkpoint store error")]
+    Checkpoint(#[from] CheckpointError),
+
+    #[error("aleph-client error")]
+    AlephClient(#[from] anyhow::Error),
+
+    #[error("event handler task failed")]
+    Join(#[from] JoinError),
+
+    #[error("no block found")]
+    BlockNotFound,
+
+    #[error("vote was included in a block that has not been finalized")]
+    VoteNotFinalized(H256),
+
+    #[error("blocks processed since block {0} have been reorged out")]
+    ReorgTooDeep(u32),
+}
+
+impl EthListenerError {
+    /// Fatal errors stop the relayer, all others only restart the listener.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::FromHex(_) | Self::AbiEncode(_) | Self::ReorgTooDeep(_) => true,
+            Self::AzeroContract(why) => why.is_fatal(),
+            Self::Checkpoint(why) => why.is_fatal(),
+            _ => false,
+        }
+    }
 }
 
 pub const ETH_BLOCK_PROD_TIME_SEC: u64 = 15;
 const ETH_LAST_BLOCK_KEY: &str = "ethereum_last_known_block_number";
+// Number of processed ranges whose last block is remembered to detect reorgs.
+const ETH_REORG_HISTORY: usize = 64;
 
 pub struct EthListener;
 
@@ -63,157 +112,841 @@ impl EthListener {
         config: Arc<Config>,
         azero_connection: Arc<SignedAzeroWsConnection>,
         eth_connection: Arc<SignedEthConnection>,
-        redis_connection: Arc<Mutex<RedisConnection>>,
+        checkpoint_store: Arc<dyn CheckpointStore>,
+        mut shutdown: ShutdownSignal,
     ) -> Result<(), EthListenerError> {
         let Config {
+            azero_contract_address,
+            azero_ref_time_limit,
+            azero_proof_size_limit,
+            gas_safety_margin_percent,
             eth_contract_address,
-            name,
+            eth_node_wss_url,
+            eth_finality,
             default_sync_from_block_eth,
+            eth_max_event_handler_tasks,
             sync_step,
             ..
         } = &*config;
 
         let address = eth_contract_address.parse::<Address>()?;
         let contract = Most::new(address, Arc::clone(&eth_connection));
+        let azero_contract = Arc::new(MostInstance::new(
+            azero_contract_address,
+            WeightLimits {
+                ref_time_limit: *azero_ref_time_limit,
+                proof_size_limit: *azero_proof_size_limit,
+                gas_safety_margin_percent: *gas_safety_margin_percent,
+            },
+        )?);
+
+        // First blocks of the ranges whose events are still being handled, plus the first block of the next range.
+        let pending_blocks: Arc<Mutex<BTreeSet<u32>>> = Arc::new(Mutex::new(BTreeSet::new()));
+        // Requests from the ranges that have been handled, whose records are pruned once the checkpoint passes them.
+        let finished_requests = Arc::new(Mutex::new(FinishedRequests::default()));
+        // Tasks handling the events from one range each. Dropping the set (e.g. when the listener fails) aborts them.
+        let mut range_tasks = JoinSet::new();
+        let event_handler_tasks_semaphore = Arc::new(Semaphore::new(*eth_max_event_handler_tasks));
+        // Votes are finalized concurrently, but sent one batch at a time, as every extrinsic takes the next nonce of
+        // the same account.
+        let vote_submission = Arc::new(Mutex::new(()));
+
+        // In the websocket mode new heads wake the listener up and logs are pushed instead of queried,
+        // except for the blocks the subscription has not covered.
+        let mut subscription = (!eth_node_wss_url.is_empty())
+            .then(|| EthLogSubscription::new(eth_node_wss_url.clone(), address));
 
         let mut first_unprocessed_block_number = read_first_unprocessed_block_number(
-            name.clone(),
-            ETH_LAST_BLOCK_KEY.to_string(),
-            redis_connection.clone(),
+            checkpoint_store.clone(),
+            ETH_LAST_BLOCK_KEY,
             *default_sync_from_block_eth,
         )
-        .await;
+        .await?;
+
+        // Last blocks of the recently processed ranges with their hashes. A block can only be reorged out if the
+        // finality policy is weaker than the chain's, in which case the affected blocks are processed again.
+        let mut processed_blocks = VecDeque::new();
+
+        health::listener_advanced(Chain::Ethereum);
+
+        pending_blocks
+            .lock()
+            .await
+            .insert(first_unprocessed_block_number);
 
         // Main Ethereum event loop.
-        loop {
+        'listen: loop {
             // Query for the next unknowns finalized block number, if not present we wait for it.
-            let next_finalized_block_number = get_next_finalized_block_number_eth(
-                eth_connection.clone(),
-                first_unprocessed_block_number,
-            )
-            .await;
+            let next_finalized_block_number = match &mut subscription {
+                Some(subscription) => {
+                    tokio::select! {
+                        _ = subscription.next_head() => {},
+                        _ = shutdown::requested(&mut shutdown) => break 'listen,
+                    };
+                    match get_finalized_block_number_eth(&eth_connection, *eth_finality).await {
+                        Ok(Some(block_number))
+                            if block_number >= first_unprocessed_block_number =>
+                        {
+                            block_number
+                        }
+                        Ok(_) => continue,
+                        Err(e) => {
+                            warn!("Client error when getting last finalized block: {e}");
+                            continue;
+                        }
+                    }
+                }
+                None => tokio::select! {
+                    block_number = get_next_finalized_block_number_eth(
+                        eth_connection.clone(),
+                        *eth_finality,
+                        first_unprocessed_block_number,
+                    ) => block_number,
+                    _ = shutdown::requested(&mut shutdown) => break 'listen,
+                },
+            };
+
+            if let Some(reorged_from) =
+                find_reorged_block(&eth_connection, &mut processed_blocks).await?
+            {
+                error!(
+                    "Ethereum blocks {reorged_from} - {} have been reorged out after being processed, processing them again",
+                    first_unprocessed_block_number - 1
+                );
+                metrics::reorg_detected(Chain::Ethereum);
+
+                // Logs pushed for the old blocks are gone, so the new ones are queried.
+                if let Some(subscription) = &mut subscription {
+                    subscription.reset();
+                }
+
+                // Ranges still being handled may overlap the reorged blocks, so they are all done before rewinding.
+                while let Some(result) = range_tasks.join_next().await {
+                    result??;
+                }
+                *pending_blocks.lock().await = BTreeSet::from([reorged_from]);
+
+                first_unprocessed_block_number = reorged_from;
+                write_last_processed_block(
+                    checkpoint_store.clone(),
+                    ETH_LAST_BLOCK_KEY,
+                    reorged_from - 1,
+                )
+                .await?;
+                metrics::set_last_processed_block(Chain::Ethereum, reorged_from - 1);
+                continue;
+            }
 
             // Don't query for more than `sync_step` blocks at one time.
-            let to_block = std::cmp::min(
+            let mut to_block = std::cmp::min(
                 next_finalized_block_number,
                 first_unprocessed_block_number + sync_step - 1,
             );
 
+            // Take events from the subscription if it has covered the whole range.
+            let mut events = None;
+            if let Some(subscription) = &mut subscription {
+                match subscription.covered_from() {
+                    Some(covered_from) if covered_from <= first_unprocessed_block_number.into() => {
+                        events = subscription
+                            .take_events(
+                                &eth_connection,
+                                first_unprocessed_block_number.into(),
+                                to_block.into(),
+                            )
+                            .await?;
+                    }
+                    // Fill the gap before the subscription with a query.
+                    Some(covered_from) => {
+                        to_block = std::cmp::min(to_block, covered_from as u32 - 1);
+                    }
+                    None => {}
+                }
+            }
+
             log::info!(
                 "Processing events from blocks {} - {}",
                 first_unprocessed_block_number,
                 to_block
             );
 
+            // Taken before the events, so that a reorg in between is noticed in the next iteration.
+            let to_block_hash = eth_connection
+                .get_block(to_block)
+                .await?
+                .and_then(|block| block.hash)
+                .ok_or(EthListenerError::BlockNotFound)?;
+
             // Query for events.
-            let events = contract
-                .events()
-                .from_block(first_unprocessed_block_number)
-                .to_block(to_block)
-                .query()
-                .await?;
+            let events = match events {
+                Some(events) => events,
+                None => {
+                    contract
+                        .events()
+                        .from_block(first_unprocessed_block_number)
+                        .to_block(to_block)
+                        .query()
+                        .await?
+                }
+            };
+            let decoded_at = Instant::now();
+
+            // Stop fetching new ranges, the ones already being handled are drained below.
+            if shutdown::is_requested(&shutdown) {
+                break 'listen;
+            }
+
+            // Add the next block number now, so that there is always some block number in the set.
+            pending_blocks.lock().await.insert(to_block + 1);
+
+            let permit = event_handler_tasks_semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Failed to acquire semaphore permit");
 
-            // Handle events.
-            for event in events {
-                handle_event(&event, &config, Arc::clone(&azero_connection)).await?
+            // Spawn a new task for handling the events from the range.
+            range_tasks.spawn(handle_events(
+                events,
+                decoded_at,
+                first_unprocessed_block_number,
+                azero_contract.clone(),
+                azero_connection.clone(),
+                checkpoint_store.clone(),
+                vote_submission.clone(),
+                pending_blocks.clone(),
+                finished_requests.clone(),
+                permit,
+            ));
+
+            // Surface errors from ranges that are done processing, so that the listener can be restarted.
+            while let Some(Some(result)) = range_tasks.join_next().now_or_never() {
+                result??;
             }
 
             // Update the last block number.
             first_unprocessed_block_number = to_block + 1;
+            processed_blocks.push_back((to_block, to_block_hash));
+            if processed_blocks.len() > ETH_REORG_HISTORY {
+                processed_blocks.pop_front();
+            }
+            health::listener_advanced(Chain::Ethereum);
+        }
+
+        info!(
+            "Waiting for events from {} ranges to be handled before shutting down",
+            range_tasks.len()
+        );
+        while let Some(result) = range_tasks.join_next().await {
+            result??;
+        }
 
-            // Cache the last processed block number.
+        // Every range task has already written its checkpoint, but the last write could have been skipped
+        // if no range was handled since the listener started or was rewound.
+        let earliest_still_pending = *pending_blocks
+            .lock()
+            .await
+            .first()
+            .expect("There should always be a pending block in the set");
+        if let Some(last_processed_block) = earliest_still_pending.checked_sub(1) {
             write_last_processed_block(
-                name.clone(),
-                ETH_LAST_BLOCK_KEY.to_string(),
-                redis_connection.clone(),
-                to_block,
+                checkpoint_store.clone(),
+                ETH_LAST_BLOCK_KEY,
+                last_processed_block,
             )
             .await?;
+            metrics::set_last_processed_block(Chain::Ethereum, last_processed_block);
+            prune_finished_requests(checkpoint_store, &finished_requests, last_processed_block)
+                .await;
         }
+        info!("Ethereum listener stopped at block {earliest_still_pending}");
+
+        Ok(())
     }
 }
 
+// Handles all events from the range of blocks starting at `from_block` and votes on their requests.
+#[allow(clippy::too_many_arguments)]
+async fn handle_events(
+    events: Vec<MostEvents>,
+    decoded_at: Instant,
+    from_block: u32,
+    azero_contract: Arc<MostInstance>,
+    azero_connection: Arc<SignedAzeroWsConnection>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    vote_submission: Arc<Mutex<()>>,
+    pending_blocks: Arc<Mutex<BTreeSet<u32>>>,
+    finished_requests: Arc<Mutex<FinishedRequests>>,
+    _permit: OwnedSemaphorePermit,
+) -> Result<(), EthListenerError> {
+    let request_hashes = events
+        .iter()
+        .filter_map(|event| match event {
+            MostEvents::CrosschainTransferRequestFilter(crosschain_transfer_event) => {
+                CrosschainRequest::try_from(crosschain_transfer_event)
+                    .ok()
+                    .map(|request| request.azero_hash())
+            }
+            _ => None,
+        })
+        .collect();
+
+    // Votes from the whole range are sent together once all events are handled.
+    let mut votes = Vec::new();
+    for event in events {
+        if let Some(vote) = handle_event(
+            &event,
+            decoded_at,
+            &azero_contract,
+            azero_connection.clone(),
+            checkpoint_store.clone(),
+        )
+        .await?
+        {
+            votes.push(vote);
+        }
+    }
+    if !votes.is_empty() {
+        vote(
+            &azero_contract,
+            azero_connection,
+            checkpoint_store.clone(),
+            &vote_submission,
+            &votes,
+        )
+        .await?;
+    }
+
+    // If any vote fails, the range stays pending and the checkpoint does not move past it.
+    let mut pending_blocks = pending_blocks.lock().await;
+    pending_blocks.remove(&from_block);
+    finished_requests
+        .lock()
+        .await
+        .add(from_block, request_hashes);
+
+    // All blocks before the first block of the earliest pending range have been processed.
+    let earliest_still_pending = pending_blocks
+        .first()
+        .expect("There should always be a pending block in the set");
+
+    // Note: `earliest_still_pending` will never be 0
+    let last_processed_block = earliest_still_pending - 1;
+    write_last_processed_block(
+        checkpoint_store.clone(),
+        ETH_LAST_BLOCK_KEY,
+        last_processed_block,
+    )
+    .await?;
+    metrics::set_last_processed_block(Chain::Ethereum, last_processed_block);
+    drop(pending_blocks);
+
+    prune_finished_requests(checkpoint_store, &finished_requests, last_processed_block).await;
+
+    Ok(())
+}
+
+// Returns the vote to send on the request from the event, if this guardian is to vote on it.
 async fn handle_event(
     event: &MostEvents,
-    config: &Config,
+    decoded_at: Instant,
+    azero_contract: &MostInstance,
     azero_connection: Arc<SignedAzeroWsConnection>,
-) -> Result<(), EthListenerError> {
-    if let MostEvents::CrosschainTransferRequestFilter(
-        crosschain_transfer_event @ CrosschainTransferRequestFilter {
-            dest_token_address,
-            amount,
-            dest_receiver_address,
-            request_nonce,
-            ..
-        },
-    ) = event
-    {
-        let Config {
-            committee_id,
-            azero_contract_address,
-            azero_contract_metadata,
-            ..
-        } = config;
+    checkpoint_store: Arc<dyn CheckpointStore>,
+) -> Result<Option<([u8; 32], CrosschainRequest)>, EthListenerError> {
+    // Completes a transfer from AlephZero, which is only measured here.
+    if let MostEvents::RequestProcessedFilter(RequestProcessedFilter { request_hash }) = event {
+        metrics::request_processed(Direction::AlephZeroToEthereum, *request_hash);
+        return Ok(None);
+    }
 
+    if let MostEvents::CrosschainTransferRequestFilter(crosschain_transfer_event) = event {
         info!("handling eth contract event: {crosschain_transfer_event:?}");
 
-        // concat bytes
-        let bytes = concat_u8_arrays(vec![
-            dest_token_address,
-            &amount.as_u128().to_le_bytes(),
-            dest_receiver_address,
-            &request_nonce.as_u128().to_le_bytes(),
-        ]);
+        // AlephZero takes `u128` values, so a request with a larger one can never be voted on there.
+        let request = match CrosschainRequest::try_from(crosschain_transfer_event) {
+            Ok(request) => request,
+            Err(why) => {
+                error!("Giving up on request {crosschain_transfer_event:?}: {why}");
+                metrics::vote_dead_lettered(Direction::EthereumToAlephZero);
+                return Ok(None);
+            }
+        };
 
-        trace!("event concatenated bytes: {bytes:?}");
+        trace!("event concatenated bytes: {:?}", request.azero_encoding());
 
-        let request_hash = keccak256(bytes);
+        let request_hash = request.azero_hash();
         debug!("hashed event encoding: {request_hash:?}");
 
-        let contract = MostInstance::new(
-            azero_contract_address,
-            azero_contract_metadata,
-            config.azero_ref_time_limit,
-            config.azero_proof_size_limit,
-        )?;
-
-        // send vote
-        contract
-            .receive_request(
-                &azero_connection,
+        // The committee id is taken from the event, so that we follow committee rotations on the source chain. It is
+        // checked before any state is recorded, as requests of other committees would never leave `Discovered`.
+        let guardian = azero_connection.account_id();
+        if !azero_contract
+            .is_in_committee(&*azero_connection, request.committee_id, guardian)
+            .await?
+        {
+            warn!(
+                "Guardian {guardian} is not a member of committee {} targeted by the request with nonce {}, skipping the vote",
+                request.committee_id, request.request_nonce
+            );
+            return Ok(None);
+        }
+
+        match read_request_state(checkpoint_store.clone(), &request_hash).await? {
+            Some(RequestState::Finalized { tx_hash }) => {
+                info!(
+                    "Vote on request 0x{} has already been finalized in tx {tx_hash:?}, skipping",
+                    hex::encode(request_hash)
+                );
+                return Ok(None);
+            }
+            Some(RequestState::Confirmed {
+                tx_hash,
+                block_hash,
+                block_number,
+                ..
+            }) => {
+                info!(
+                    "Resuming the vote on request 0x{} included in block {block_number}",
+                    hex::encode(request_hash)
+                );
+                finalize_vote(
+                    azero_connection,
+                    checkpoint_store,
+                    request_hash,
+                    tx_hash,
+                    block_hash,
+                    block_number as u32,
+                )
+                .await?;
+                return Ok(None);
+            }
+            Some(RequestState::Submitted { .. })
+            | Some(RequestState::Discovered)
+            | Some(RequestState::Failed { .. }) => {}
+            Some(RequestState::Skipped { reason })
+            | Some(RequestState::DeadLettered { reason }) => {
+                info!(
+                    "Vote on request 0x{} has been skipped: {reason}",
+                    hex::encode(request_hash)
+                );
+                return Ok(None);
+            }
+            None => {
+                write_request_state(
+                    checkpoint_store.clone(),
+                    &request_hash,
+                    &RequestState::Discovered,
+                )
+                .await?;
+            }
+        }
+
+        // Sending a vote the contract does not need would only emit `SignedProcessedRequest` or fail.
+        let skip_reason = if azero_contract
+            .is_request_processed(&*azero_connection, request_hash)
+            .await?
+        {
+            Some(SkipReason::AlreadyProcessed)
+        } else if azero_contract
+            .has_signed_request(&*azero_connection, request_hash, guardian)
+            .await?
+        {
+            Some(SkipReason::AlreadySigned)
+        } else {
+            None
+        };
+        if let Some(skip_reason) = skip_reason {
+            info!(
+                "Not voting on request 0x{}: {skip_reason:?}",
+                hex::encode(request_hash)
+            );
+            metrics::vote_skipped(Direction::EthereumToAlephZero, skip_reason);
+            write_request_state(
+                checkpoint_store,
+                &request_hash,
+                &RequestState::Skipped {
+                    reason: format!("{skip_reason:?}"),
+                },
+            )
+            .await?;
+            return Ok(None);
+        }
+
+        metrics::request_discovered(request_hash, decoded_at);
+        return Ok(Some((request_hash, request)));
+    }
+
+    Ok(None)
+}
+
+// Sends this guardian's votes to AlephZero in as few extrinsics as possible and waits for them to be finalized.
+async fn vote(
+    azero_contract: &MostInstance,
+    azero_connection: Arc<SignedAzeroWsConnection>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    vote_submission: &Mutex<()>,
+    votes: &[([u8; 32], CrosschainRequest)],
+) -> Result<(), EthListenerError> {
+    let submission = vote_submission.lock().await;
+    info!("Sending {} votes to AlephZero", votes.len());
+    for _ in votes {
+        metrics::vote_submitted(Direction::EthereumToAlephZero);
+    }
+
+    // A failed vote does not hold back the others, the first error is returned once they are all finalized.
+    let mut first_error: Option<EthListenerError> = None;
+    // Votes included in blocks, each batch is sent only after the previous one is included.
+    let mut sent_votes = Vec::new();
+    let mut remaining = votes;
+    while !remaining.is_empty() {
+        let batch = match azero_contract
+            .prepare_votes(&azero_connection, remaining)
+            .await
+        {
+            Ok(batch) => batch,
+            Err(why) => {
+                // It is not known which votes would fit into the batch, so none of the remaining ones is sent.
+                for (request_hash, _) in remaining {
+                    fail_vote(checkpoint_store.clone(), request_hash, &why).await?;
+                }
+                first_error.get_or_insert(why.into());
+                break;
+            }
+        };
+        let (batch_votes, rest) = remaining.split_at(batch.size);
+        remaining = rest;
+
+        let tx_info = match send_votes(
+            azero_contract,
+            &azero_connection,
+            checkpoint_store.clone(),
+            &batch,
+            batch_votes,
+        )
+        .await
+        {
+            Ok(tx_info) => tx_info,
+            Err(why) => {
+                for ((request_hash, _), outcome) in batch_votes.iter().zip(&batch.outcomes) {
+                    if outcome.is_ok() {
+                        fail_vote(checkpoint_store.clone(), request_hash, &why).await?;
+                    }
+                }
+                first_error.get_or_insert(why);
+                None
+            }
+        };
+
+        for ((request_hash, _), outcome) in batch_votes.iter().zip(batch.outcomes) {
+            match (outcome, &tx_info) {
+                (Ok(()), Some(tx_info)) => sent_votes.push((*request_hash, tx_info.clone())),
+                // Already marked as failed along with the whole batch.
+                (Ok(()), None) => {}
+                (Err(why), _) => {
+                    if let Some(why) =
+                        reject_vote(checkpoint_store.clone(), request_hash, why).await?
+                    {
+                        first_error.get_or_insert(why.into());
+                    }
+                }
+            }
+        }
+    }
+    drop(submission);
+
+    for (request_hash, tx_info) in sent_votes {
+        if let Err(why) = confirm_vote(
+            azero_connection.clone(),
+            checkpoint_store.clone(),
+            request_hash,
+            tx_info,
+        )
+        .await
+        {
+            warn!(
+                "Vote on request 0x{} has not been finalized: {why:?}",
+                hex::encode(request_hash)
+            );
+            first_error.get_or_insert(why);
+        }
+    }
+
+    match first_error {
+        Some(why) => Err(why),
+        None => Ok(()),
+    }
+}
+
+// Sends the votes of a batch that have passed the dry-run and waits for them to be included in a block. They are marked
+// as submitted in between, as the extrinsic may still be included if the relayer stops before that.
+async fn send_votes(
+    azero_contract: &MostInstance,
+    azero_connection: &SignedAzeroWsConnection,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    batch: &VoteBatch,
+    batch_votes: &[([u8; 32], CrosschainRequest)],
+) -> Result<Option<TxInfo>, EthListenerError> {
+    let Some(submitted_tx) = azero_contract
+        .receive_requests(azero_connection, batch)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let tx_hash = H256(submitted_tx.tx_hash().0);
+    for ((request_hash, _), outcome) in batch_votes.iter().zip(&batch.outcomes) {
+        if outcome.is_ok() {
+            write_request_state(
+                checkpoint_store.clone(),
+                request_hash,
+                &RequestState::Submitted {
+                    tx_hash,
+                    resubmissions: 0,
+                },
+            )
+            .await?;
+        }
+    }
+
+    Ok(Some(submitted_tx.wait_for_in_block().await?))
+}
+
+// Records the block a vote is included in and waits for it to be finalized.
+async fn confirm_vote(
+    azero_connection: Arc<SignedAzeroWsConnection>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    request_hash: [u8; 32],
+    tx_info: TxInfo,
+) -> Result<(), EthListenerError> {
+    let block_number = azero_connection
+        .get_block_number(tx_info.block_hash)
+        .await?
+        .ok_or(EthListenerError::BlockNotFound)?;
+    let tx_hash = H256(tx_info.tx_hash.0);
+    let block_hash = H256(tx_info.block_hash.0);
+
+    write_request_state(
+        checkpoint_store.clone(),
+        &request_hash,
+        &RequestState::Confirmed {
+            tx_hash,
+            block_hash,
+            block_number: block_number.into(),
+            resubmissions: 0,
+        },
+    )
+    .await?;
+
+    finalize_vote(
+        azero_connection,
+        checkpoint_store,
+        request_hash,
+        tx_hash,
+        block_hash,
+        block_number,
+    )
+    .await
+}
+
+// Records a vote that has not been sent, so that it is retried.
+async fn fail_vote(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    request_hash: &[u8; 32],
+    why: &impl Debug,
+) -> Result<(), CheckpointError> {
+    warn!(
+        "Vote on request 0x{} has not been sent: {why:?}",
+        hex::encode(request_hash)
+    );
+    metrics::vote_failed(Direction::EthereumToAlephZero);
+    write_request_state(
+        checkpoint_store,
+        request_hash,
+        &RequestState::Failed {
+            reason: format!("{why:?}"),
+        },
+    )
+    .await
+}
+
+// Whether voting again on the request cannot help, so it should be dead-lettered for an operator.
+fn is_permanent_vote_error(why: &AzeroContractError) -> bool {
+    match why {
+        AzeroContractError::Most(most_error) => !is_retriable_vote_error(most_error),
+        // The vote does not fit into an extrinsic even on its own.
+        AzeroContractError::WeightLimitExceeded(_) => true,
+        _ => false,
+    }
+}
+
+// Records a vote rejected in the dry-run. The request is skipped if the vote is no longer needed and dead-lettered
+// for an operator if it can never succeed. Returns the error if the vote is to be retried.
+async fn reject_vote(
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    request_hash: &[u8; 32],
+    why: AzeroContractError,
+) -> Result<Option<AzeroContractError>, CheckpointError> {
+    let skip_reason = match &why {
+        AzeroContractError::Most(MostError::RequestAlreadySigned) => SkipReason::AlreadySigned,
+        AzeroContractError::Most(MostError::RequestAlreadyProcessed) => {
+            SkipReason::AlreadyProcessed
+        }
+        _ if is_permanent_vote_error(&why) => {
+            error!(
+                "Vote on request 0x{} cannot succeed: {why:?}, giving up on the request",
+                hex::encode(request_hash)
+            );
+            metrics::vote_failed(Direction::EthereumToAlephZero);
+            metrics::vote_dead_lettered(Direction::EthereumToAlephZero);
+            metrics::request_abandoned(*request_hash);
+            write_request_state(
+                checkpoint_store,
                 request_hash,
-                *committee_id as u128,
-                *dest_token_address,
-                amount.as_u128(),
-                *dest_receiver_address,
-                request_nonce.as_u128(),
+                &RequestState::DeadLettered {
+                    reason: format!("{why:?}"),
+                },
             )
             .await?;
+            return Ok(None);
+        }
+        _ => {
+            fail_vote(checkpoint_store, request_hash, &why).await?;
+            return Ok(Some(why));
+        }
+    };
+
+    info!(
+        "Not voting on request 0x{}: {skip_reason:?}",
+        hex::encode(request_hash)
+    );
+    metrics::vote_skipped(Direction::EthereumToAlephZero, skip_reason);
+    metrics::request_abandoned(*request_hash);
+    write_request_state(
+        checkpoint_store,
+        request_hash,
+        &RequestState::Skipped {
+            reason: format!("{skip_reason:?}"),
+        },
+    )
+    .await?;
+    Ok(None)
+}
+
+// Waits until the block with our vote is finalized and makes sure it has not been replaced by a fork in the meantime.
+async fn finalize_vote(
+    azero_connection: Arc<SignedAzeroWsConnection>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    request_hash: [u8; 32],
+    tx_hash: H256,
+    block_hash: H256,
+    block_number: u32,
+) -> Result<(), EthListenerError> {
+    get_next_finalized_block_number_azero(azero_connection.clone(), block_number).await?;
+
+    let finalized_block_hash = azero_connection
+        .get_block_hash(block_number)
+        .await?
+        .ok_or(EthListenerError::BlockNotFound)?;
+
+    if finalized_block_hash.0 != block_hash.0 {
+        metrics::vote_failed(Direction::EthereumToAlephZero);
+        write_request_state(
+            checkpoint_store,
+            &request_hash,
+            &RequestState::Failed {
+                reason: format!("block {block_number} with tx {tx_hash:?} was not finalized"),
+            },
+        )
+        .await?;
+        return Err(EthListenerError::VoteNotFinalized(tx_hash));
     }
 
+    info!("Azero tx {tx_hash:?} finalized");
+
+    write_request_state(
+        checkpoint_store,
+        &request_hash,
+        &RequestState::Finalized { tx_hash },
+    )
+    .await?;
+    metrics::vote_succeeded(Direction::EthereumToAlephZero);
+
     Ok(())
 }
 
+// Returns the block right after the last processed range that is still canonical, if any of the processed ranges has
+// been reorged out. Only the last range is checked, unless it has been reorged out itself.
+async fn find_reorged_block(
+    eth_connection: &SignedEthConnection,
+    processed_blocks: &mut VecDeque<(u32, H256)>,
+) -> Result<Option<u32>, EthListenerError> {
+    let mut reorged_block = None;
+    while let Some(&(block_number, block_hash)) = processed_blocks.back() {
+        let canonical_hash = eth_connection
+            .get_block(block_number)
+            .await?
+            .and_then(|block| block.hash);
+        if canonical_hash == Some(block_hash) {
+            break;
+        }
+        reorged_block = Some(block_number);
+        processed_blocks.pop_back();
+    }
+
+    match (reorged_block, processed_blocks.back()) {
+        (None, _) => Ok(None),
+        (Some(_), Some((block_number, _))) => Ok(Some(block_number + 1)),
+        // None of the remembered blocks is canonical any more, the checkpoint has to be rewound manually.
+        (Some(block_number), None) => Err(EthListenerError::ReorgTooDeep(block_number)),
+    }
+}
+
+// Returns the last block that is final under the given policy.
+async fn get_finalized_block_number_eth(
+    eth_connection: &SignedEthConnection,
+    finality: FinalityPolicy,
+) -> Result<Option<u32>, ProviderError> {
+    let tag = match finality {
+        FinalityPolicy::Finalized => BlockNumber::Finalized,
+        FinalityPolicy::Safe => BlockNumber::Safe,
+        FinalityPolicy::Confirmations(confirmations) => {
+            let latest_block_number = eth_connection.get_block_number().await?.as_u32();
+            return Ok((latest_block_number + 1)
+                .checked_sub(confirmations)
+                .map(|block_number| {
+                    metrics::set_last_finalized_block(Chain::Ethereum, block_number);
+                    block_number
+                }));
+        }
+    };
+
+    let best_finalized_block_number = eth_connection
+        .get_block(tag)
+        .await?
+        .and_then(|block| block.number)
+        .map(|number| number.as_u32());
+    if let Some(block_number) = best_finalized_block_number {
+        metrics::set_last_finalized_block(Chain::Ethereum, block_number);
+    }
+    Ok(best_finalized_block_number)
+}
+
 pub async fn get_next_finalized_block_number_eth(
     eth_connection: Arc<SignedEthConnection>,
+    finality: FinalityPolicy,
     not_older_than: u32,
 ) -> u32 {
     loop {
-        match eth_connection.get_block(BlockNumber::Finalized).await {
-            Ok(block) => match block {
-                Some(block) => {
-                    let best_finalized_block_number = block
-                        .number
-                        .expect("Finalized block should have a number.")
-                        .as_u32();
-                    if best_finalized_block_number >= not_older_than {
-                        return best_finalized_block_number;
-                    }
+        match get_finalized_block_number_eth(&eth_connection, finality).await {
+            Ok(Some(best_finalized_block_number)) => {
+                if best_finalized_block_number >= not_older_than {
+                    return best_finalized_block_number;
                 }
-                None => {
-                    warn!("No finalized block found.");
-                }
-            },
+            }
+            Ok(None) => {
+                warn!("No finalized block found.");
+            }
             Err(e) => {
                 warn!("Client error when getting last finalized block: {e}");
             }
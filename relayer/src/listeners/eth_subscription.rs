@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+
+use ethers::{
+    contract::parse_log,
+    core::types::Address,
+    providers::{Middleware, Provider, ProviderError, StreamExt, Ws},
+    types::{Filter, Log, U256},
+};
+use log::{info, warn};
+use most_client::eth::MostEvents;
+use tokio::{
+    sync::mpsc::{error::TryRecvError, unbounded_channel, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+    time::{sleep, timeout, Duration},
+};
+
+use crate::{connections::eth::SignedEthConnection, listeners::eth::ETH_BLOCK_PROD_TIME_SEC};
+
+// If no new head is pushed for this long, the subscription is assumed to be broken.
+const NEW_HEADS_TIMEOUT_SEC: u64 = 5 * ETH_BLOCK_PROD_TIME_SEC;
+
+enum Notification {
+    NewHead(u64),
+    Log(Log),
+}
+
+/// New heads and `Most` logs pushed by an Ethereum node over a websocket.
+///
+/// Logs are buffered until the caller asks for a finalized range. Logs only arrive for blocks imported after
+/// subscribing, so ranges starting before [`EthLogSubscription::covered_from`] have to be queried instead. The same
+/// applies after the websocket breaks: it is reopened on the next call, and the buffer starts over.
+pub struct EthLogSubscription {
+    url: String,
+    address: Address,
+    task: Option<(JoinHandle<()>, UnboundedReceiver<Notification>)>,
+    covered_from: Option<u64>,
+    buffer: BTreeMap<(u64, U256), Log>,
+}
+
+impl EthLogSubscription {
+    pub fn new(url: String, address: Address) -> Self {
+        Self {
+            url,
+            address,
+            task: None,
+            covered_from: None,
+            buffer: BTreeMap::new(),
+        }
+    }
+
+    /// First block whose logs are all in the buffer, known once the first new head has arrived.
+    pub fn covered_from(&self) -> Option<u64> {
+        self.covered_from
+    }
+
+    /// Waits for the next new head. Falls back to waiting one block time if the subscription is broken.
+    pub async fn next_head(&mut self) {
+        if self.task.is_none() {
+            let (sender, receiver) = unbounded_channel();
+            let task = tokio::spawn(forward_notifications(
+                self.url.clone(),
+                self.address,
+                sender,
+            ));
+            self.task = Some((task, receiver));
+        }
+
+        let (_, receiver) = self.task.as_mut().expect("Subscribed above");
+        loop {
+            match timeout(Duration::from_secs(NEW_HEADS_TIMEOUT_SEC), receiver.recv()).await {
+                Ok(Some(Notification::NewHead(block_number))) => {
+                    self.covered_from.get_or_insert(block_number + 1);
+                    return;
+                }
+                Ok(Some(Notification::Log(log))) => buffer_log(&mut self.buffer, log),
+                Ok(None) => {
+                    warn!("Ethereum websocket subscription has been closed");
+                    break;
+                }
+                Err(_) => {
+                    warn!("No new Ethereum head received for {NEW_HEADS_TIMEOUT_SEC}s");
+                    break;
+                }
+            }
+        }
+
+        self.reset();
+        sleep(Duration::from_secs(ETH_BLOCK_PROD_TIME_SEC)).await;
+    }
+
+    /// Removes the buffered events from blocks up to `to_block` and returns those from blocks starting at `from_block`.
+    /// Returns `None` if the subscription has broken in the meantime, as the buffer may be incomplete then.
+    ///
+    /// Every log is checked against the receipt returned by `eth_connection`, which cross-checks it across nodes,
+    /// so that a log from a reorged block or a misbehaving websocket node is never acted upon.
+    pub async fn take_events(
+        &mut self,
+        eth_connection: &SignedEthConnection,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Option<Vec<MostEvents>>, ProviderError> {
+        if let Some((_, receiver)) = &mut self.task {
+            loop {
+                match receiver.try_recv() {
+                    Ok(Notification::Log(log)) => buffer_log(&mut self.buffer, log),
+                    Ok(Notification::NewHead(_)) => {}
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        warn!("Ethereum websocket subscription has been closed");
+                        self.reset();
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+
+        let later_blocks = self.buffer.split_off(&(to_block + 1, U256::zero()));
+        let logs = std::mem::replace(&mut self.buffer, later_blocks);
+
+        let mut events = Vec::new();
+        for ((block_number, _), log) in logs {
+            if block_number < from_block {
+                continue;
+            }
+            if !is_canonical(eth_connection, &log).await? {
+                warn!("Dropping log {log:?}, it is not part of the canonical chain");
+                continue;
+            }
+            match parse_log::<MostEvents>(log) {
+                Ok(event) => events.push(event),
+                Err(why) => warn!("Cannot decode a Most log: {why}"),
+            }
+        }
+
+        Ok(Some(events))
+    }
+
+    /// Drops the subscription together with the buffer. It is reopened on the next call to [`Self::next_head`].
+    pub fn reset(&mut self) {
+        if let Some((task, _)) = self.task.take() {
+            task.abort();
+        }
+        self.covered_from = None;
+        self.buffer.clear();
+    }
+}
+
+impl Drop for EthLogSubscription {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+fn buffer_log(buffer: &mut BTreeMap<(u64, U256), Log>, log: Log) {
+    let (Some(block_number), Some(log_index)) = (log.block_number, log.log_index) else {
+        return;
+    };
+    let key = (block_number.as_u64(), log_index);
+
+    // The node re-sends logs from blocks that have been reorged out with `removed` set.
+    if log.removed == Some(true) {
+        buffer.remove(&key);
+    } else {
+        buffer.insert(key, log);
+    }
+}
+
+async fn is_canonical(
+    eth_connection: &SignedEthConnection,
+    log: &Log,
+) -> Result<bool, ProviderError> {
+    let Some(tx_hash) = log.transaction_hash else {
+        return Ok(false);
+    };
+
+    Ok(
+        match eth_connection.get_transaction_receipt(tx_hash).await? {
+            Some(receipt) => {
+                receipt.block_hash == log.block_hash
+                    && receipt.logs.iter().any(|receipt_log| {
+                        receipt_log.log_index == log.log_index
+                            && receipt_log.address == log.address
+                            && receipt_log.topics == log.topics
+                            && receipt_log.data == log.data
+                    })
+            }
+            None => false,
+        },
+    )
+}
+
+// Subscribes to `Most` logs before new heads, so that logs from every block announced afterwards are delivered.
+async fn forward_notifications(
+    url: String,
+    address: Address,
+    sender: UnboundedSender<Notification>,
+) {
+    let provider = match Provider::<Ws>::connect(url.as_str()).await {
+        Ok(provider) => provider,
+        Err(why) => {
+            warn!("Cannot connect to the Ethereum websocket {url}: {why}");
+            return;
+        }
+    };
+
+    let filter = Filter::new().address(address);
+    let mut logs = match provider.subscribe_logs(&filter).await {
+        Ok(logs) => logs,
+        Err(why) => {
+            warn!("Cannot subscribe to Most logs: {why}");
+            return;
+        }
+    };
+    let mut heads = match provider.subscribe_blocks().await {
+        Ok(heads) => heads,
+        Err(why) => {
+            warn!("Cannot subscribe to new Ethereum heads: {why}");
+            return;
+        }
+    };
+
+    info!("Subscribed to new Ethereum heads and Most logs at {url}");
+
+    loop {
+        let notification = tokio::select! {
+            Some(log) = logs.next() => Notification::Log(log),
+            Some(head) = heads.next() => match head.number {
+                Some(block_number) => Notification::NewHead(block_number.as_u64()),
+                None => continue,
+            },
+            else => return,
+        };
+
+        if sender.send(notification).is_err() {
+            return;
+        }
+    }
+}
@@ -1,5 +1,8 @@
 mod azero;
 mod eth;
+mod eth_subscription;
+mod supervisor;
 
 pub use azero::*;
 pub use eth::*;
+pub use supervisor::supervise;
@@ -0,0 +1,66 @@
+use std::{fmt::Display, future::Future, sync::Arc};
+
+use log::{error, info, warn};
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::{
+    config::Config,
+    shutdown::{self, ShutdownSignal},
+};
+
+/// Runs a listener until it returns a fatal error, restarting it with an exponential backoff otherwise.
+///
+/// Every run is spawned as a separate task, so that a panic inside a listener is treated as a transient failure too.
+/// The backoff is reset once a run has lasted longer than the maximum backoff.
+/// Returns `Ok` once the listener has stopped after a shutdown request.
+pub async fn supervise<E, F, Fut>(
+    name: &'static str,
+    config: Arc<Config>,
+    mut shutdown: ShutdownSignal,
+    mut start: F,
+    is_fatal: fn(&E) -> bool,
+) -> Result<(), E>
+where
+    E: Display + Send + 'static,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+{
+    let min_backoff = Duration::from_secs(config.listener_restart_min_backoff_secs);
+    let max_backoff = Duration::from_secs(config.listener_restart_max_backoff_secs);
+    let mut backoff = min_backoff;
+
+    loop {
+        info!("Starting {name} listener");
+        let started_at = Instant::now();
+
+        match tokio::spawn(start()).await {
+            Ok(Ok(())) if shutdown::is_requested(&shutdown) => {
+                info!("{name} listener has shut down");
+                return Ok(());
+            }
+            Ok(Ok(())) => warn!("{name} listener has stopped unexpectedly"),
+            Ok(Err(why)) if is_fatal(&why) => {
+                error!("{name} listener has failed with a fatal error: {why}");
+                return Err(why);
+            }
+            Ok(Err(why)) => warn!("{name} listener has failed: {why}"),
+            Err(why) => error!("{name} listener has panicked: {why}"),
+        }
+
+        if shutdown::is_requested(&shutdown) {
+            warn!("Not restarting {name} listener, shutdown has been requested");
+            return Ok(());
+        }
+
+        if started_at.elapsed() > max_backoff {
+            backoff = min_backoff;
+        }
+
+        info!("Restarting {name} listener in {backoff:?}");
+        tokio::select! {
+            _ = sleep(backoff) => {}
+            _ = shutdown::requested(&mut shutdown) => return Ok(()),
+        }
+        backoff = std::cmp::min(backoff * 2, max_backoff);
+    }
+}
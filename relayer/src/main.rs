@@ -1,28 +1,40 @@
-use std::{env, process, sync::Arc};
+use std::{env, process::ExitCode, sync::Arc};
 
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use config::Config;
 use connections::EthConnectionError;
 use ethers::signers::{coins_bip39::English, LocalWallet, MnemonicBuilder, Signer, WalletError};
 use eyre::Result;
+use futures::TryFutureExt;
 use log::{debug, error, info};
-use redis::Client as RedisClient;
 use thiserror::Error;
-use tokio::{runtime::Runtime, sync::Mutex};
+use tokio::{
+    runtime::Runtime,
+    task::JoinSet,
+    time::{timeout, Duration},
+};
+use zeroize::Zeroize;
 
 use crate::{
-    connections::{azero, eth},
-    listeners::{AlephZeroListener, AzeroListenerError, EthListener, EthListenerError},
+    connections::{eth, AzeroConnectionPool},
+    health::ReadinessCheck,
+    listeners::{supervise, AlephZeroListener, AzeroListenerError, EthListener, EthListenerError},
 };
 
+mod checkpoints;
 mod config;
 mod connections;
-mod contracts;
-mod helpers;
+mod health;
+mod keys;
 mod listeners;
+mod metrics;
+mod server;
+mod shutdown;
 
 const DEV_MNEMONIC: &str =
     "harsh master island dirt equip search awesome double turn crush wool grant";
+// Exit code used when in-flight requests could not be drained within `shutdown_timeout_secs`.
+const SHUTDOWN_TIMEOUT_EXIT_CODE: u8 = 2;
 
 #[derive(Debug, Error)]
 #[error(transparent)]
@@ -37,109 +49,206 @@ pub enum ListenerError {
     #[error("eth wallet error")]
     EthWallet(#[from] WalletError),
 
-    #[error("eth listener error")]
+    #[error("azero listener error")]
     Azero(#[from] AzeroListenerError),
 }
 
-fn main() -> Result<()> {
-    let config = Arc::new(Config::parse());
+fn main() -> Result<ExitCode> {
+    let mut config = Config::parse();
+    if let Err(why) = config.validate() {
+        Config::command()
+            .error(ErrorKind::MissingRequiredArgument, why)
+            .exit();
+    }
 
     env::set_var("RUST_LOG", config.rust_log.as_str());
     env_logger::init();
 
+    // Load the signing keys before anything else so that the secrets can be wiped from memory right away.
+    let azero_keypair = if config.dev {
+        let azero_seed = "//".to_owned() + &config.dev_account_index.to_string();
+        aleph_client::keypair_from_string(&azero_seed)
+    } else {
+        keys::azero_keypair(&config).expect("Cannot load Aleph Zero signing key")
+    };
+
+    let wallet = if config.dev {
+        // If no keystore path is provided, we use the default development mnemonic
+        MnemonicBuilder::<English>::default()
+            .phrase(DEV_MNEMONIC)
+            .index(config.dev_account_index)
+            .expect("Provided index is an integer between 0 and 9")
+            .build()
+            .expect("Mnemonic is correct")
+    } else {
+        assert!(
+            !config.eth_keystore_path.is_empty(),
+            "Keystore path must be provided unless relayer is run in dev mode"
+        );
+
+        LocalWallet::decrypt_keystore(
+            &config.eth_keystore_path,
+            config.eth_keystore_password.expose(),
+        )
+        .expect("Cannot decrypt eth wallet")
+    };
+    config.eth_keystore_password.zeroize();
+
+    let config = Arc::new(config);
+
     info!("{:#?}", &config);
 
     let rt = Runtime::new()?;
 
     rt.block_on(async {
-        let mut tasks = Vec::with_capacity(4);
-
-        let client = RedisClient::open(config.redis_node.clone())
-            .expect("Cannot connect to the redis cluster instance");
-        let redis_connection = Arc::new(Mutex::new(client.get_async_connection().await.unwrap()));
-
-        let azero_keypair = if config.dev {
-            let azero_seed = "//".to_owned() + &config.dev_account_index.to_string();
-            aleph_client::keypair_from_string(&azero_seed)
-        } else {
-            unimplemented!("Only dev mode is supported for now");
-        };
-
-        let azero_connection = Arc::new(azero::sign(
-            &azero::init(&config.azero_node_wss_url).await,
-            &azero_keypair,
-        ));
+        let mut shutdown = shutdown::listen_for_signals()?;
+        metrics::register();
 
-        debug!("Established connection to Aleph Zero node");
+        let checkpoint_store = checkpoints::open(&config)
+            .await
+            .expect("Cannot open the checkpoint store");
 
-        let wallet = if config.dev {
-            // If no keystore path is provided, we use the default development mnemonic
-            MnemonicBuilder::<English>::default()
-                .phrase(DEV_MNEMONIC)
-                .index(config.dev_account_index)
-                .expect("Provided index is an integer between 0 and 9")
-                .build()
-                .expect("Mnemonic is correct")
-        } else {
-            assert!(
-                !config.eth_keystore_path.is_empty(),
-                "Keystore path must be provided unless relayer is run in dev mode"
-            );
+        let azero_connections = Arc::new(AzeroConnectionPool::new(
+            config.azero_node_wss_urls.clone(),
+            azero_keypair,
+        ));
+        azero_connections.connection().await;
 
-            LocalWallet::decrypt_keystore(&config.eth_keystore_path, &config.eth_keystore_password)
-                .expect("Cannot decrypt eth wallet")
-        };
+        debug!("Established connection to Aleph Zero node");
 
         log::info!("Wallet address: {}", wallet.address());
 
         let eth_connection = Arc::new(
-            eth::sign(eth::connect(&config.eth_node_http_url).await, wallet)
-                .await
-                .expect("Cannot sign the connection"),
+            eth::sign(
+                eth::connect(&config.eth_node_http_urls, config.eth_rpc_quorum)
+                    .await
+                    .expect("Cannot connect to the Ethereum nodes"),
+                wallet,
+            )
+            .await
+            .expect("Cannot sign the connection"),
         );
 
         debug!("Established connection to Ethereum node");
 
-        let config_rc1 = Arc::clone(&config);
-        let azero_connection_rc1 = Arc::clone(&azero_connection);
-        let eth_connection_rc1 = Arc::clone(&eth_connection);
-        let redis_connection_rc1 = Arc::clone(&redis_connection);
-
-        log::info!("Starting Ethereum listener");
+        let readiness = Arc::new(ReadinessCheck {
+            config: Arc::clone(&config),
+            azero_connections: Arc::clone(&azero_connections),
+            eth_connection: Arc::clone(&eth_connection),
+            checkpoint_store: Arc::clone(&checkpoint_store),
+        });
+        let http_server = server::run(config.http_address.clone(), readiness, shutdown.clone());
+        tokio::spawn(async move {
+            if let Err(why) = http_server.await {
+                error!("HTTP server has failed: {why}");
+            }
+        });
+
+        let mut listeners = JoinSet::new();
+
+        {
+            let config = Arc::clone(&config);
+            let azero_connections = Arc::clone(&azero_connections);
+            let eth_connection = Arc::clone(&eth_connection);
+            let checkpoint_store = Arc::clone(&checkpoint_store);
+            let listener_shutdown = shutdown.clone();
+
+            listeners.spawn(
+                supervise(
+                    "Ethereum",
+                    Arc::clone(&config),
+                    shutdown.clone(),
+                    move || {
+                        let config = config.clone();
+                        let azero_connections = azero_connections.clone();
+                        let eth_connection = eth_connection.clone();
+                        let checkpoint_store = checkpoint_store.clone();
+                        let listener_shutdown = listener_shutdown.clone();
+
+                        // Get a working connection on every (re)start.
+                        async move {
+                            EthListener::run(
+                                config,
+                                azero_connections.connection().await,
+                                eth_connection,
+                                checkpoint_store,
+                                listener_shutdown,
+                            )
+                            .await
+                        }
+                    },
+                    EthListenerError::is_fatal,
+                )
+                .map_err(ListenerError::from),
+            );
+        }
 
-        tasks.push(tokio::spawn(async {
-            EthListener::run(
-                config_rc1,
-                azero_connection_rc1,
-                eth_connection_rc1,
-                redis_connection_rc1,
-            )
-            .await
-            .expect("Ethereum listener task has failed")
-        }));
-
-        let config_rc2 = Arc::clone(&config);
-        let azero_connection_rc2 = Arc::clone(&azero_connection);
-        let eth_connection_rc2 = Arc::clone(&eth_connection);
-        let redis_connection_rc2 = Arc::clone(&redis_connection);
-
-        log::info!("Starting AlephZero listener");
-
-        tasks.push(tokio::spawn(async {
-            AlephZeroListener::run(
-                config_rc2,
-                azero_connection_rc2,
-                eth_connection_rc2,
-                redis_connection_rc2,
-            )
-            .await
-            .expect("AlephZero listener task has failed")
-        }));
+        {
+            let config = Arc::clone(&config);
+            let azero_connections = Arc::clone(&azero_connections);
+            let eth_connection = Arc::clone(&eth_connection);
+            let checkpoint_store = Arc::clone(&checkpoint_store);
+            let listener_shutdown = shutdown.clone();
+
+            listeners.spawn(
+                supervise(
+                    "AlephZero",
+                    Arc::clone(&config),
+                    shutdown.clone(),
+                    move || {
+                        let config = config.clone();
+                        let azero_connections = azero_connections.clone();
+                        let eth_connection = eth_connection.clone();
+                        let checkpoint_store = checkpoint_store.clone();
+                        let listener_shutdown = listener_shutdown.clone();
+
+                        // Get a working connection on every (re)start.
+                        async move {
+                            AlephZeroListener::run(
+                                config,
+                                azero_connections.connection().await,
+                                eth_connection,
+                                checkpoint_store,
+                                listener_shutdown,
+                            )
+                            .await
+                        }
+                    },
+                    AzeroListenerError::is_fatal,
+                )
+                .map_err(ListenerError::from),
+            );
+        }
 
-        for t in tasks {
-            t.await.expect("task failure");
+        // Listeners only return on their own after a fatal error, which stops the whole relayer.
+        tokio::select! {
+            Some(result) = listeners.join_next() => {
+                result.expect("Listener supervisor has panicked")?;
+            }
+            _ = shutdown::requested(&mut shutdown) => {}
         }
-    });
 
-    process::exit(-1);
+        let drained = timeout(Duration::from_secs(config.shutdown_timeout_secs), async {
+            while let Some(result) = listeners.join_next().await {
+                result.expect("Listener supervisor has panicked")?;
+            }
+            Ok::<(), ListenerError>(())
+        })
+        .await;
+
+        match drained {
+            Ok(result) => {
+                result?;
+                info!("Relayer has shut down gracefully");
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(_) => {
+                error!(
+                    "In-flight requests were not handled within {}s, they will be resumed after a restart",
+                    config.shutdown_timeout_secs
+                );
+                Ok(ExitCode::from(SHUTDOWN_TIMEOUT_EXIT_CODE))
+            }
+        }
+    })
 }
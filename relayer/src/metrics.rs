@@ -0,0 +1,389 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+    time::Instant,
+};
+
+use ethers::types::U256;
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_counter, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Counter, Encoder, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder,
+};
+use tokio::sync::Semaphore;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Chain {
+    AlephZero,
+    Ethereum,
+}
+
+impl Chain {
+    fn label(&self) -> &'static str {
+        match self {
+            Chain::AlephZero => "alephzero",
+            Chain::Ethereum => "ethereum",
+        }
+    }
+}
+
+/// Direction of a transfer, from the source chain to the destination chain.
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    AlephZeroToEthereum,
+    EthereumToAlephZero,
+}
+
+impl Direction {
+    fn label(&self) -> &'static str {
+        match self {
+            Direction::AlephZeroToEthereum => "alephzero_to_ethereum",
+            Direction::EthereumToAlephZero => "ethereum_to_alephzero",
+        }
+    }
+}
+
+/// Why a vote has not been sent.
+#[derive(Debug, Clone, Copy)]
+pub enum SkipReason {
+    AlreadyProcessed,
+    AlreadySigned,
+}
+
+impl SkipReason {
+    fn label(&self) -> &'static str {
+        match self {
+            SkipReason::AlreadyProcessed => "already_processed",
+            SkipReason::AlreadySigned => "already_signed",
+        }
+    }
+}
+
+static LAST_PROCESSED_BLOCK: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "most_relayer_last_processed_block",
+        "Last block whose events have all been handled",
+        &["chain"]
+    )
+    .expect("Metric can be registered")
+});
+
+static LAST_FINALIZED_BLOCK: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "most_relayer_last_finalized_block",
+        "Last finalized block seen by the listener",
+        &["chain"]
+    )
+    .expect("Metric can be registered")
+});
+
+static BLOCK_LAG: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "most_relayer_block_lag",
+        "Number of finalized blocks that have not been processed yet",
+        &["chain"]
+    )
+    .expect("Metric can be registered")
+});
+
+static LARGE_BLOCKS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "most_relayer_large_blocks_total",
+        "Blocks with unusually many requests, whose votes are queued on the event handler semaphore",
+        &["chain"]
+    )
+    .expect("Metric can be registered")
+});
+
+static REORGS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "most_relayer_reorgs_total",
+        "Reorgs of blocks that had already been processed",
+        &["chain"]
+    )
+    .expect("Metric can be registered")
+});
+
+static VOTES_SUBMITTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "most_relayer_votes_submitted_total",
+        "Votes sent to the destination chain",
+        &["direction"]
+    )
+    .expect("Metric can be registered")
+});
+
+static VOTES_SUCCEEDED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "most_relayer_votes_succeeded_total",
+        "Votes finalized on the destination chain",
+        &["direction"]
+    )
+    .expect("Metric can be registered")
+});
+
+static VOTES_FAILED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "most_relayer_votes_failed_total",
+        "Votes that could not be sent or finalized",
+        &["direction"]
+    )
+    .expect("Metric can be registered")
+});
+
+static VOTES_SKIPPED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "most_relayer_votes_skipped_total",
+        "Votes not sent, as the destination contract does not need them",
+        &["direction", "reason"]
+    )
+    .expect("Metric can be registered")
+});
+
+static VOTES_RESUBMITTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "most_relayer_votes_resubmitted_total",
+        "Votes sent again after their transaction had been dropped",
+        &["direction"]
+    )
+    .expect("Metric can be registered")
+});
+
+static VOTES_DEAD_LETTERED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "most_relayer_votes_dead_lettered_total",
+        "Votes given up on, as the destination contract rejects them for good",
+        &["direction"]
+    )
+    .expect("Metric can be registered")
+});
+
+static ETH_GAS_USED: Lazy<Counter> = Lazy::new(|| {
+    register_counter!(
+        "most_relayer_eth_gas_used_total",
+        "Gas used by the guardian's transactions on Ethereum"
+    )
+    .expect("Metric can be registered")
+});
+
+static ETH_FEES_PAID: Lazy<Counter> = Lazy::new(|| {
+    register_counter!(
+        "most_relayer_eth_fees_paid_wei_total",
+        "Fees paid for the guardian's transactions on Ethereum, in wei"
+    )
+    .expect("Metric can be registered")
+});
+
+static ETH_FEE_BUMPS: Lazy<Counter> = Lazy::new(|| {
+    register_counter!(
+        "most_relayer_eth_fee_bumps_total",
+        "Stuck transactions of the guardian on Ethereum replaced with higher fees"
+    )
+    .expect("Metric can be registered")
+});
+
+static ETH_VOTES_STUCK_AT_FEE_CAP: Lazy<Counter> = Lazy::new(|| {
+    register_counter!(
+        "most_relayer_eth_votes_stuck_at_fee_cap_total",
+        "Votes of the guardian on Ethereum given up on after not being mined with fees at the cap"
+    )
+    .expect("Metric can be registered")
+});
+
+static REQUEST_PROCESSING_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "most_relayer_request_processing_seconds",
+        "Time from decoding a request event to seeing the finalized RequestProcessed event on the destination chain",
+        &["direction"],
+        vec![30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1200.0, 1800.0, 3600.0, 7200.0]
+    )
+    .expect("Metric can be registered")
+});
+
+static EVENT_HANDLER_PERMITS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "most_relayer_event_handler_available_permits",
+        "Available permits of the AlephZero event handler semaphore"
+    )
+    .expect("Metric can be registered")
+});
+
+// Requests this guardian has voted on, but that have not been processed on the destination chain yet.
+static PENDING_REQUESTS: Lazy<Mutex<HashMap<[u8; 32], Instant>>> = Lazy::new(Default::default);
+const MAX_PENDING_REQUESTS: usize = 10_000;
+
+// The semaphore is recreated when the AlephZero listener restarts, so only a weak reference is kept.
+static EVENT_HANDLER_SEMAPHORE: Lazy<Mutex<Weak<Semaphore>>> = Lazy::new(Default::default);
+
+/// Registers all metrics, so that they are exported before their first update.
+pub fn register() {
+    Lazy::force(&LAST_PROCESSED_BLOCK);
+    Lazy::force(&LAST_FINALIZED_BLOCK);
+    Lazy::force(&BLOCK_LAG);
+    Lazy::force(&REORGS);
+    Lazy::force(&LARGE_BLOCKS);
+    Lazy::force(&VOTES_SUBMITTED);
+    Lazy::force(&VOTES_SUCCEEDED);
+    Lazy::force(&VOTES_FAILED);
+    Lazy::force(&VOTES_SKIPPED);
+    Lazy::force(&VOTES_RESUBMITTED);
+    Lazy::force(&VOTES_DEAD_LETTERED);
+    Lazy::force(&ETH_GAS_USED);
+    Lazy::force(&ETH_FEES_PAID);
+    Lazy::force(&ETH_FEE_BUMPS);
+    Lazy::force(&ETH_VOTES_STUCK_AT_FEE_CAP);
+    Lazy::force(&REQUEST_PROCESSING_TIME);
+    Lazy::force(&EVENT_HANDLER_PERMITS);
+}
+
+pub fn set_last_processed_block(chain: Chain, block_number: u32) {
+    LAST_PROCESSED_BLOCK
+        .with_label_values(&[chain.label()])
+        .set(block_number.into());
+    update_lag(chain);
+}
+
+pub fn set_last_finalized_block(chain: Chain, block_number: u32) {
+    LAST_FINALIZED_BLOCK
+        .with_label_values(&[chain.label()])
+        .set(block_number.into());
+    update_lag(chain);
+}
+
+fn update_lag(chain: Chain) {
+    let finalized = LAST_FINALIZED_BLOCK
+        .with_label_values(&[chain.label()])
+        .get();
+    let processed = LAST_PROCESSED_BLOCK
+        .with_label_values(&[chain.label()])
+        .get();
+    BLOCK_LAG
+        .with_label_values(&[chain.label()])
+        .set((finalized - processed).max(0));
+}
+
+pub fn block_lag(chain: Chain) -> i64 {
+    BLOCK_LAG.with_label_values(&[chain.label()]).get()
+}
+
+pub fn reorg_detected(chain: Chain) {
+    REORGS.with_label_values(&[chain.label()]).inc();
+}
+
+pub fn large_block(chain: Chain) {
+    LARGE_BLOCKS.with_label_values(&[chain.label()]).inc();
+}
+
+pub fn vote_submitted(direction: Direction) {
+    VOTES_SUBMITTED
+        .with_label_values(&[direction.label()])
+        .inc();
+}
+
+pub fn vote_succeeded(direction: Direction) {
+    VOTES_SUCCEEDED
+        .with_label_values(&[direction.label()])
+        .inc();
+}
+
+pub fn vote_failed(direction: Direction) {
+    VOTES_FAILED.with_label_values(&[direction.label()]).inc();
+}
+
+pub fn vote_skipped(direction: Direction, reason: SkipReason) {
+    VOTES_SKIPPED
+        .with_label_values(&[direction.label(), reason.label()])
+        .inc();
+}
+
+pub fn vote_resubmitted(direction: Direction) {
+    VOTES_RESUBMITTED
+        .with_label_values(&[direction.label()])
+        .inc();
+}
+
+pub fn vote_dead_lettered(direction: Direction) {
+    VOTES_DEAD_LETTERED
+        .with_label_values(&[direction.label()])
+        .inc();
+}
+
+pub fn eth_gas_spent(gas_used: U256, effective_gas_price: U256) {
+    ETH_GAS_USED.inc_by(gas_used.low_u64() as f64);
+    ETH_FEES_PAID.inc_by((gas_used.saturating_mul(effective_gas_price)).low_u128() as f64);
+}
+
+pub fn eth_fee_bumped() {
+    ETH_FEE_BUMPS.inc();
+}
+
+pub fn eth_vote_stuck_at_fee_cap() {
+    ETH_VOTES_STUCK_AT_FEE_CAP.inc();
+}
+
+/// Starts measuring the processing time of a request this guardian votes on, from `decoded_at` when its event has been
+/// decoded. Keyed by the hash checked on the destination chain.
+///
+/// Requests whose `RequestProcessed` event is never seen, e.g. because it was emitted while the relayer was down,
+/// are evicted oldest first once there are `MAX_PENDING_REQUESTS` of them.
+pub fn request_discovered(request_hash: [u8; 32], decoded_at: Instant) {
+    let mut pending_requests = PENDING_REQUESTS.lock().expect("Lock is not poisoned");
+    if pending_requests.len() >= MAX_PENDING_REQUESTS
+        && !pending_requests.contains_key(&request_hash)
+    {
+        let oldest = pending_requests
+            .iter()
+            .min_by_key(|(_, discovered_at)| **discovered_at)
+            .map(|(request_hash, _)| *request_hash);
+        if let Some(oldest) = oldest {
+            pending_requests.remove(&oldest);
+        }
+    }
+    pending_requests.entry(request_hash).or_insert(decoded_at);
+}
+
+/// Stops measuring a request that will not be processed with this guardian's vote, e.g. as it has been skipped or
+/// dead-lettered.
+pub fn request_abandoned(request_hash: [u8; 32]) {
+    PENDING_REQUESTS
+        .lock()
+        .expect("Lock is not poisoned")
+        .remove(&request_hash);
+}
+
+/// Records the processing time of a request, if this guardian has voted on it since the relayer started.
+pub fn request_processed(direction: Direction, request_hash: [u8; 32]) {
+    let discovered_at = PENDING_REQUESTS
+        .lock()
+        .expect("Lock is not poisoned")
+        .remove(&request_hash);
+    if let Some(discovered_at) = discovered_at {
+        REQUEST_PROCESSING_TIME
+            .with_label_values(&[direction.label()])
+            .observe(discovered_at.elapsed().as_secs_f64());
+    }
+}
+
+pub fn watch_event_handler_semaphore(semaphore: &Arc<Semaphore>) {
+    *EVENT_HANDLER_SEMAPHORE
+        .lock()
+        .expect("Lock is not poisoned") = Arc::downgrade(semaphore);
+}
+
+/// Renders all metrics in the Prometheus text format.
+pub fn render() -> Vec<u8> {
+    if let Some(semaphore) = EVENT_HANDLER_SEMAPHORE
+        .lock()
+        .expect("Lock is not poisoned")
+        .upgrade()
+    {
+        EVENT_HANDLER_PERMITS.set(semaphore.available_permits() as i64);
+    }
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("Metrics can be encoded");
+    buffer
+}
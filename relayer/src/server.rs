@@ -0,0 +1,90 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use log::{info, warn};
+use thiserror::Error;
+
+use crate::{
+    health::{self, ReadinessCheck},
+    metrics,
+    shutdown::{self, ShutdownSignal},
+};
+
+#[derive(Debug, Error)]
+#[error(transparent)]
+#[non_exhaustive]
+pub enum ServerError {
+    #[error("invalid listen address")]
+    Address(#[from] std::net::AddrParseError),
+
+    #[error("http server error")]
+    Hyper(#[from] hyper::Error),
+}
+
+/// Serves the Prometheus `/metrics` endpoint together with the `/healthz` (liveness) and `/readyz` (readiness) probes
+/// until a shutdown is requested.
+pub async fn run(
+    address: String,
+    readiness: Arc<ReadinessCheck>,
+    mut shutdown: ShutdownSignal,
+) -> Result<(), ServerError> {
+    let address: SocketAddr = address.parse()?;
+    health::start_clock();
+
+    let make_service = make_service_fn(move |_| {
+        let readiness = readiness.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle_request(request, readiness.clone())
+            }))
+        }
+    });
+    let server = Server::try_bind(&address)?.serve(make_service);
+
+    info!("Serving metrics and health checks on http://{address}");
+
+    server
+        .with_graceful_shutdown(async move { shutdown::requested(&mut shutdown).await })
+        .await?;
+
+    Ok(())
+}
+
+async fn handle_request(
+    request: Request<Body>,
+    readiness: Arc<ReadinessCheck>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(metrics::render())),
+        (&Method::GET, "/healthz") => {
+            check_response("liveness", health::liveness(&readiness.config))
+        }
+        (&Method::GET, "/readyz") => check_response("readiness", readiness.run().await),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+
+    Ok(response.expect("Response is valid"))
+}
+
+fn check_response(
+    name: &str,
+    result: Result<(), Vec<String>>,
+) -> Result<Response<Body>, hyper::http::Error> {
+    match result {
+        Ok(()) => Response::builder().body(Body::from("ok\n")),
+        Err(problems) => {
+            warn!("{name} check failed: {problems:?}");
+            Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(Body::from(problems.join("\n") + "\n"))
+        }
+    }
+}
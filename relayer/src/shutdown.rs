@@ -0,0 +1,51 @@
+use std::{future::pending, io, process};
+
+use log::{error, info};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+
+/// Flips to `true` once the relayer has been asked to shut down.
+pub type ShutdownSignal = watch::Receiver<bool>;
+
+// Exit code used when the relayer is killed by a second signal, as a shell would report a SIGINT.
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// Installs SIGINT and SIGTERM handlers. The first signal starts a graceful shutdown, the second one exits immediately.
+pub fn listen_for_signals() -> io::Result<ShutdownSignal> {
+    let (sender, receiver) = watch::channel(false);
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+
+    tokio::spawn(async move {
+        for attempt in 0.. {
+            tokio::select! {
+                _ = sigint.recv() => info!("Received SIGINT"),
+                _ = sigterm.recv() => info!("Received SIGTERM"),
+            }
+
+            if attempt == 0 {
+                info!("Shutting down gracefully, send the signal again to exit immediately");
+                sender.send_replace(true);
+            } else {
+                error!("Exiting without waiting for in-flight requests");
+                process::exit(FORCED_EXIT_CODE);
+            }
+        }
+    });
+
+    Ok(receiver)
+}
+
+pub fn is_requested(shutdown: &ShutdownSignal) -> bool {
+    *shutdown.borrow()
+}
+
+/// Resolves once a shutdown has been requested.
+pub async fn requested(shutdown: &mut ShutdownSignal) {
+    if shutdown.wait_for(|requested| *requested).await.is_err() {
+        // Signal handling is gone, so there will be no shutdown request.
+        pending::<()>().await;
+    }
+}